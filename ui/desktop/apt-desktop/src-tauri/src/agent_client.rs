@@ -0,0 +1,562 @@
+// HTTP client for APT agent servers.
+//
+// Speaks the API of `src/agents/agent_server_async.py`: synchronous
+// `/execute` for short jobs, and `/execute/async` plus `/jobs/...` polling
+// for long ones. Tokens may be `${secret:<name>}` references; they are
+// resolved against the vault for each request and never stored resolved.
+
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+use zeroize::Zeroizing;
+
+use crate::model::{Extra, Priority};
+use crate::vault::{Vault, VaultError};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Job submission and status requests return right away on the agent.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Extra time a synchronous `/execute` gets on top of the job timeout.
+const EXECUTE_MARGIN: Duration = Duration::from_secs(30);
+/// The agent's default job timeout, and the most `/execute` accepts.
+const DEFAULT_JOB_TIMEOUT: u64 = 300;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AgentError {
+    #[error(transparent)]
+    Vault(#[from] VaultError),
+    #[error("invalid agent endpoint '{0}'")]
+    InvalidEndpoint(String),
+    #[error("agent '{0}' has no endpoint")]
+    NoEndpoint(String),
+    #[error("environment variable '{0}' is not set")]
+    UnsetVariable(String),
+    #[error("could not reach agent at {0}: {1}")]
+    Unreachable(String, String),
+    #[error("agent at {0} did not respond in time")]
+    Timeout(String),
+    #[error("agent at {0} rejected the auth token")]
+    Unauthorized(String),
+    #[error("job '{0}' does not exist")]
+    JobNotFound(String),
+    #[error("job '{0}' has not finished: {1}")]
+    NotReady(String, String),
+    #[error("agent at {0} has a full job queue: {1}")]
+    QueueFull(String, String),
+    #[error("agent at {0} answered {1}: {2}")]
+    Rejected(String, StatusCode, String),
+    #[error("unexpected response from agent at {0}: {1}")]
+    InvalidResponse(String, String),
+}
+
+impl AgentError {
+    fn kind(&self) -> &'static str {
+        match self {
+            AgentError::Vault(_) => "vault",
+            AgentError::InvalidEndpoint(_) => "invalid_endpoint",
+            AgentError::NoEndpoint(_) => "no_endpoint",
+            AgentError::UnsetVariable(_) => "variable_not_set",
+            AgentError::Unreachable(..) => "unreachable",
+            AgentError::Timeout(_) => "timeout",
+            AgentError::Unauthorized(_) => "unauthorized",
+            AgentError::JobNotFound(_) => "job_not_found",
+            AgentError::NotReady(..) => "not_ready",
+            AgentError::QueueFull(..) => "queue_full",
+            AgentError::Rejected(..) => "rejected",
+            AgentError::InvalidResponse(..) => "invalid_response",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// job id for job errors, the agent or variable name where there is no
+// endpoint, and the agent endpoint otherwise.
+impl Serialize for AgentError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            AgentError::Vault(e) => return e.serialize(serializer),
+            AgentError::InvalidEndpoint(path)
+            | AgentError::NoEndpoint(path)
+            | AgentError::UnsetVariable(path)
+            | AgentError::Unreachable(path, _)
+            | AgentError::Timeout(path)
+            | AgentError::Unauthorized(path)
+            | AgentError::JobNotFound(path)
+            | AgentError::NotReady(path, _)
+            | AgentError::QueueFull(path, _)
+            | AgentError::Rejected(path, ..)
+            | AgentError::InvalidResponse(path, _) => path,
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path: Some(path),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Pending,
+    Running,
+    Complete,
+    Failed,
+    Cancelled,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecuteRequest {
+    pub code: String,
+    #[serde(default)]
+    pub context: IndexMap<String, Value>,
+    /// Seconds.
+    #[serde(default = "default_job_timeout")]
+    pub timeout: u64,
+    /// Sent as the agent's numeric priority; the agent uses `normal` when
+    /// unset.
+    #[serde(default, serialize_with = "serialize_priority")]
+    pub priority: Option<Priority>,
+}
+
+fn default_job_timeout() -> u64 {
+    DEFAULT_JOB_TIMEOUT
+}
+
+/// Status of a job, as returned by `/execute/async` and `/jobs/{id}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    /// Percent.
+    pub progress: Option<f64>,
+    pub message: Option<String>,
+    /// 1-based; only set while the job is queued.
+    pub queue_position: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobResults {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub result: Value,
+    /// Seconds.
+    pub execution_time: Option<f64>,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobSummary {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub created_at: String,
+    pub progress: Option<f64>,
+    #[serde(deserialize_with = "deserialize_priority")]
+    pub priority: Priority,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub queued: u32,
+    pub running: u32,
+    pub max_concurrent: u32,
+    pub max_queued: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobList {
+    pub jobs: Vec<JobSummary>,
+    pub stats: QueueStats,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JobCounts {
+    pub queued: u32,
+    pub running: u32,
+    pub complete: u32,
+    pub failed: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgentStats {
+    pub agent_id: String,
+    pub max_concurrent_jobs: u32,
+    pub max_queued_jobs: u32,
+    pub current_running: u32,
+    pub current_queued: u32,
+    pub available_slots: i64,
+    pub total_jobs: u32,
+    #[serde(default)]
+    pub jobs_by_status: JobCounts,
+}
+
+/// `/health` of an async agent. Agents running the older synchronous
+/// server answer with a different set of fields, which end up in `extra`.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgentHealth {
+    pub status: String,
+    pub agent_id: Option<String>,
+    pub active_jobs: Option<u32>,
+    pub queued_jobs: Option<u32>,
+    pub total_jobs: Option<u32>,
+    pub max_concurrent: Option<u32>,
+    pub available_slots: Option<i64>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// Shared HTTP connection pool for agent requests.
+pub struct AgentHttp {
+    client: reqwest::Client,
+}
+
+impl AgentHttp {
+    pub fn new() -> Result<AgentHttp, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(AgentHttp { client })
+    }
+
+    /// A client for the agent at `endpoint`. `auth_token` may contain
+    /// `${secret:<name>}` references, which need an unlocked vault.
+    pub fn agent(
+        &self,
+        vault: &Vault,
+        endpoint: &str,
+        auth_token: Option<&str>,
+    ) -> Result<AgentClient, AgentError> {
+        let base = Url::parse(endpoint.trim())
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+            .ok_or_else(|| AgentError::InvalidEndpoint(endpoint.to_string()))?;
+        let auth_token = auth_token
+            .filter(|token| !token.is_empty())
+            .map(|token| vault.expand(token).map(Zeroizing::new))
+            .transpose()?;
+
+        Ok(AgentClient {
+            http: self.client.clone(),
+            endpoint: endpoint.trim().trim_end_matches('/').to_string(),
+            base,
+            auth_token,
+            timeout: None,
+        })
+    }
+}
+
+pub struct AgentClient {
+    http: reqwest::Client,
+    /// As configured, for error messages.
+    endpoint: String,
+    base: Url,
+    auth_token: Option<Zeroizing<String>>,
+    timeout: Option<Duration>,
+}
+
+impl AgentClient {
+    /// Overrides the request timeout, except for synchronous `/execute`.
+    pub fn with_timeout(mut self, timeout: Duration) -> AgentClient {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub async fn health(&self) -> Result<AgentHealth, AgentError> {
+        self.send(self.request(Method::GET, &["health"]), None)
+            .await
+    }
+
+    /// Runs a job synchronously. The agent refuses jobs with a timeout of
+    /// more than five minutes here; use `submit` for those.
+    pub async fn execute(&self, request: &ExecuteRequest) -> Result<Value, AgentError> {
+        let timeout = Duration::from_secs(request.timeout) + EXECUTE_MARGIN;
+        self.send(
+            self.request(Method::POST, &["execute"])
+                .json(request)
+                .timeout(timeout),
+            None,
+        )
+        .await
+    }
+
+    /// Queues a job; fails with `QueueFull` when the agent has no room.
+    pub async fn submit(&self, request: &ExecuteRequest) -> Result<Job, AgentError> {
+        self.send(
+            self.request(Method::POST, &["execute", "async"])
+                .json(request),
+            None,
+        )
+        .await
+    }
+
+    pub async fn job(&self, job_id: &str) -> Result<Job, AgentError> {
+        self.send(self.request(Method::GET, &["jobs", job_id]), Some(job_id))
+            .await
+    }
+
+    /// Results of a complete job; fails with `NotReady` while it is queued
+    /// or running.
+    pub async fn job_results(&self, job_id: &str) -> Result<JobResults, AgentError> {
+        self.send(
+            self.request(Method::GET, &["jobs", job_id, "results"]),
+            Some(job_id),
+        )
+        .await
+    }
+
+    /// Cancels a queued or running job and deletes it with its results.
+    pub async fn delete_job(&self, job_id: &str) -> Result<(), AgentError> {
+        self.send::<Value>(
+            self.request(Method::DELETE, &["jobs", job_id]),
+            Some(job_id),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub async fn jobs(&self) -> Result<JobList, AgentError> {
+        self.send(self.request(Method::GET, &["jobs"]), None).await
+    }
+
+    pub async fn stats(&self) -> Result<AgentStats, AgentError> {
+        self.send(self.request(Method::GET, &["stats"]), None).await
+    }
+
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.base.clone();
+        // Cannot fail: the endpoint was checked to be an http(s) URL.
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        let mut request = self.http.request(method, url);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        match &self.auth_token {
+            Some(token) => request.bearer_auth(token.as_str()),
+            None => request,
+        }
+    }
+
+    /// Sends a request and decodes a successful response; `job_id` names
+    /// the job a 404 or 425 refers to.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        job_id: Option<&str>,
+    ) -> Result<T, AgentError> {
+        let response = request.send().await.map_err(|e| self.transport_error(e))?;
+        let response = self.check_status(response, job_id).await?;
+        response.json().await.map_err(|e| {
+            if e.is_timeout() {
+                AgentError::Timeout(self.endpoint.clone())
+            } else {
+                AgentError::InvalidResponse(self.endpoint.clone(), e.to_string())
+            }
+        })
+    }
+
+    async fn check_status(
+        &self,
+        response: Response,
+        job_id: Option<&str>,
+    ) -> Result<Response, AgentError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let detail = error_detail(response).await;
+        let endpoint = self.endpoint.clone();
+        Err(match (status, job_id) {
+            (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN, _) => {
+                AgentError::Unauthorized(endpoint)
+            }
+            (StatusCode::NOT_FOUND, Some(job_id)) => AgentError::JobNotFound(job_id.to_string()),
+            (StatusCode::TOO_EARLY, Some(job_id)) => {
+                AgentError::NotReady(job_id.to_string(), detail)
+            }
+            (StatusCode::TOO_MANY_REQUESTS, _) => AgentError::QueueFull(endpoint, detail),
+            _ => AgentError::Rejected(endpoint, status, detail),
+        })
+    }
+
+    fn transport_error(&self, error: reqwest::Error) -> AgentError {
+        if error.is_timeout() {
+            AgentError::Timeout(self.endpoint.clone())
+        } else {
+            // reqwest's own message only names the URL; the cause, such as
+            // "Connection refused", is at the end of the source chain.
+            let mut cause: &dyn std::error::Error = &error;
+            while let Some(source) = cause.source() {
+                cause = source;
+            }
+            AgentError::Unreachable(self.endpoint.clone(), cause.to_string())
+        }
+    }
+}
+
+/// FastAPI puts the reason of an error response in `detail`.
+async fn error_detail(response: Response) -> String {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        detail: Value,
+    }
+
+    let text = response.text().await.unwrap_or_default();
+    match serde_json::from_str::<ErrorBody>(&text) {
+        Ok(ErrorBody {
+            detail: Value::String(detail),
+        }) => detail,
+        Ok(ErrorBody { detail }) => detail.to_string(),
+        Err(_) => text,
+    }
+}
+
+// The agent numbers priorities from `low` (0) to `urgent` (3).
+
+fn priority_number(priority: Priority) -> u8 {
+    match priority {
+        Priority::Low => 0,
+        Priority::Normal => 1,
+        Priority::High => 2,
+        Priority::Urgent => 3,
+    }
+}
+
+fn serialize_priority<S: Serializer>(
+    priority: &Option<Priority>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    priority.map(priority_number).serialize(serializer)
+}
+
+fn deserialize_priority<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Priority, D::Error> {
+    Ok(match u8::deserialize(deserializer)? {
+        0 => Priority::Low,
+        1 => Priority::Normal,
+        2 => Priority::High,
+        _ => Priority::Urgent,
+    })
+}
+
+#[tauri::command]
+pub async fn get_agent_health(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+) -> Result<AgentHealth, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .health()
+        .await
+}
+
+#[tauri::command]
+pub async fn execute_on_agent(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+    request: ExecuteRequest,
+) -> Result<Value, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .execute(&request)
+        .await
+}
+
+#[tauri::command]
+pub async fn submit_agent_job(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+    request: ExecuteRequest,
+) -> Result<Job, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .submit(&request)
+        .await
+}
+
+#[tauri::command]
+pub async fn get_agent_job(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+    job_id: String,
+) -> Result<Job, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .job(&job_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn get_agent_job_results(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+    job_id: String,
+) -> Result<JobResults, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .job_results(&job_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn delete_agent_job(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+    job_id: String,
+) -> Result<(), AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .delete_job(&job_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn list_agent_jobs(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+) -> Result<JobList, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .jobs()
+        .await
+}
+
+#[tauri::command]
+pub async fn get_agent_stats(
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    endpoint: String,
+    auth_token: Option<String>,
+) -> Result<AgentStats, AgentError> {
+    http.agent(&vault, &endpoint, auth_token.as_deref())?
+        .stats()
+        .await
+}
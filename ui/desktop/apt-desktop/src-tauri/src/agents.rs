@@ -0,0 +1,469 @@
+// Registry of the remote agents the app knows about.
+//
+// Agents are stored in `agents.json` under the app data directory, keyed by
+// name. Besides how to reach an agent over HTTP, an entry records how it is
+// deployed and how to log in to its host, which deploys and log tailing
+// need. Entries can also be imported from the `agents:` section of a test
+// definition.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use indexmap::IndexMap;
+use reqwest::Url;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_with::skip_serializing_none;
+
+use crate::agent_client::{AgentClient, AgentError, AgentHttp};
+use crate::model::{self, AgentDefinition};
+use crate::variables::Environment;
+use crate::vault::Vault;
+use crate::versions;
+use crate::workspace::{Workspace, WorkspaceError};
+
+const AGENTS_FILE: &str = "agents.json";
+const MAX_NAME_LEN: usize = 64;
+const DEFAULT_SSH_USER: &str = "root";
+const DEFAULT_SSH_PORT: u16 = 22;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error("agent '{0}' does not exist")]
+    NotFound(String),
+    #[error("agent '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("invalid agent name '{0}'")]
+    InvalidName(String),
+    #[error("invalid endpoint for agent '{0}': {1}")]
+    InvalidEndpoint(String, String),
+    #[error("invalid SSH target for agent '{0}': {1}")]
+    InvalidSshTarget(String, String),
+    #[error("invalid test definition: {0}")]
+    InvalidDefinition(String),
+}
+
+impl RegistryError {
+    fn kind(&self) -> &'static str {
+        match self {
+            RegistryError::Workspace(_) => "workspace",
+            RegistryError::NotFound(_) => "agent_not_found",
+            RegistryError::AlreadyExists(_) => "already_exists",
+            RegistryError::InvalidName(_) => "invalid_name",
+            RegistryError::InvalidEndpoint(..) => "invalid_endpoint",
+            RegistryError::InvalidSshTarget(..) => "invalid_ssh_target",
+            RegistryError::InvalidDefinition(_) => "invalid_definition",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// agent name.
+impl Serialize for RegistryError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            RegistryError::Workspace(e) => return e.serialize(serializer),
+            RegistryError::NotFound(name)
+            | RegistryError::AlreadyExists(name)
+            | RegistryError::InvalidName(name)
+            | RegistryError::InvalidEndpoint(name, _)
+            | RegistryError::InvalidSshTarget(name, _) => Some(name.as_str()),
+            RegistryError::InvalidDefinition(_) => None,
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// How the agent is installed on its host; the choices of `aptcli agent
+/// create --type`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeploymentType {
+    Docker,
+    Cron,
+    Systemd,
+    Shell,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentMode {
+    /// Pushes metrics to InfluxDB on a schedule.
+    Emit,
+    /// Serves the HTTP job API.
+    #[default]
+    Serve,
+}
+
+/// Host the agent runs on, as in `aptcli agent deploy --target
+/// user@host:port --ssh-key ...`.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SshTarget {
+    pub host: String,
+    #[serde(default = "default_ssh_user")]
+    pub user: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    /// Private key; the SSH agent and default keys are used when unset.
+    pub key_file: Option<PathBuf>,
+}
+
+fn default_ssh_user() -> String {
+    DEFAULT_SSH_USER.to_string()
+}
+
+fn default_ssh_port() -> u16 {
+    DEFAULT_SSH_PORT
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Unknown for agents imported from a test definition.
+    pub deployment: Option<DeploymentType>,
+    #[serde(default)]
+    pub mode: AgentMode,
+    /// May be a `${VAR}` reference, as in test definitions.
+    pub endpoint: Option<String>,
+    /// Usually a `${secret:<name>}` or `${VAR}` reference rather than the
+    /// token itself.
+    pub auth_token: Option<String>,
+    pub ssh: Option<SshTarget>,
+    #[serde(default)]
+    pub labels: IndexMap<String, String>,
+    /// Virtual users the agent can drive.
+    pub capacity: Option<u32>,
+    /// Seconds.
+    pub timeout: Option<u64>,
+    /// Seconds.
+    pub health_check_interval: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgentInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: AgentConfig,
+}
+
+impl AgentInfo {
+    /// A client for the agent. `${VAR}` references in its endpoint and token
+    /// are resolved against `environment`, secrets against the vault.
+    pub fn client(
+        &self,
+        http: &AgentHttp,
+        vault: &Vault,
+        environment: &Environment,
+    ) -> Result<AgentClient, AgentError> {
+        let endpoint = self
+            .config
+            .endpoint
+            .as_deref()
+            .ok_or_else(|| AgentError::NoEndpoint(self.name.clone()))?;
+        let endpoint = environment
+            .expand(endpoint)
+            .map_err(AgentError::UnsetVariable)?;
+        let auth_token = self
+            .config
+            .auth_token
+            .as_deref()
+            .map(|token| environment.expand(token))
+            .transpose()
+            .map_err(AgentError::UnsetVariable)?;
+        http.agent(vault, &endpoint, auth_token.as_deref())
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct AgentsConfig {
+    #[serde(default)]
+    agents: IndexMap<String, AgentConfig>,
+}
+
+#[derive(Default, Serialize)]
+pub struct ImportSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// Already registered and not replaced.
+    pub skipped: Vec<String>,
+}
+
+pub struct AgentRegistry {
+    config: RwLock<AgentsConfig>,
+    path: PathBuf,
+}
+
+impl AgentRegistry {
+    pub fn load(data_dir: &Path) -> AgentRegistry {
+        let path = data_dir.join(AGENTS_FILE);
+        let config = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        AgentRegistry {
+            config: RwLock::new(config),
+            path,
+        }
+    }
+
+    pub fn list(&self) -> Vec<AgentInfo> {
+        self.config
+            .read()
+            .unwrap()
+            .agents
+            .iter()
+            .map(|(name, config)| AgentInfo {
+                name: name.clone(),
+                config: config.clone(),
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<AgentInfo, RegistryError> {
+        self.config
+            .read()
+            .unwrap()
+            .agents
+            .get(name)
+            .map(|config| AgentInfo {
+                name: name.to_string(),
+                config: config.clone(),
+            })
+            .ok_or_else(|| RegistryError::NotFound(name.to_string()))
+    }
+
+    pub fn create(&self, agent: AgentInfo) -> Result<(), RegistryError> {
+        let name = check_name(&agent.name)?;
+        check_config(name, &agent.config)?;
+        self.update(|config| {
+            if config.agents.contains_key(name) {
+                return Err(RegistryError::AlreadyExists(name.to_string()));
+            }
+            config.agents.insert(name.to_string(), agent.config);
+            Ok(())
+        })
+    }
+
+    /// Replaces the entry for `name`; `agent.name` may rename it.
+    pub fn replace(&self, name: &str, agent: AgentInfo) -> Result<(), RegistryError> {
+        let new_name = check_name(&agent.name)?;
+        check_config(new_name, &agent.config)?;
+        self.update(|config| {
+            let index = config
+                .agents
+                .get_index_of(name)
+                .ok_or_else(|| RegistryError::NotFound(name.to_string()))?;
+            if new_name != name && config.agents.contains_key(new_name) {
+                return Err(RegistryError::AlreadyExists(new_name.to_string()));
+            }
+            config.agents.shift_remove_index(index);
+            config
+                .agents
+                .shift_insert(index, new_name.to_string(), agent.config);
+            Ok(())
+        })
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), RegistryError> {
+        self.update(|config| {
+            config
+                .agents
+                .shift_remove(name)
+                .map(|_| ())
+                .ok_or_else(|| RegistryError::NotFound(name.to_string()))
+        })
+    }
+
+    /// Registers the agents of a test definition. Agents that already exist
+    /// are skipped, or with `replace` get the definition's endpoint, token
+    /// and timeouts while keeping their deployment settings.
+    pub fn import(
+        &self,
+        agents: &IndexMap<String, AgentDefinition>,
+        replace: bool,
+    ) -> Result<ImportSummary, RegistryError> {
+        for name in agents.keys() {
+            check_name(name)?;
+        }
+
+        let mut summary = ImportSummary::default();
+        self.update(|config| {
+            for (name, definition) in agents {
+                let name = name.trim();
+                match config.agents.get_mut(name) {
+                    Some(_) if !replace => summary.skipped.push(name.to_string()),
+                    Some(existing) => {
+                        apply_definition(existing, definition);
+                        check_config(name, existing)?;
+                        summary.updated.push(name.to_string());
+                    }
+                    None => {
+                        let mut imported = AgentConfig::default();
+                        apply_definition(&mut imported, definition);
+                        check_config(name, &imported)?;
+                        config.agents.insert(name.to_string(), imported);
+                        summary.added.push(name.to_string());
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        Ok(summary)
+    }
+
+    /// Applies `change` and saves the result; nothing is changed in memory
+    /// when either step fails.
+    fn update(
+        &self,
+        change: impl FnOnce(&mut AgentsConfig) -> Result<(), RegistryError>,
+    ) -> Result<(), RegistryError> {
+        let mut config = self.config.write().unwrap();
+        let mut updated = config.clone();
+        change(&mut updated)?;
+
+        let content = serde_json::to_string_pretty(&updated)
+            .map_err(|e| WorkspaceError::InvalidPath(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| WorkspaceError::io(dir, e))?;
+        }
+        versions::atomic_write(&self.path, content.as_bytes())?;
+        // Tokens are meant to be references, but nothing stops a literal one.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| WorkspaceError::io(&self.path, e))?;
+        }
+
+        *config = updated;
+        Ok(())
+    }
+}
+
+fn apply_definition(config: &mut AgentConfig, definition: &AgentDefinition) {
+    config.endpoint = definition.endpoint.clone();
+    config.auth_token = definition.auth_token.clone();
+    config.timeout = definition.timeout;
+    config.health_check_interval = definition.health_check_interval;
+}
+
+/// Names end up in remote paths and unit names when deploying, so they are
+/// limited to letters, digits, `-`, `_` and `.`.
+pub fn check_name(name: &str) -> Result<&str, RegistryError> {
+    let trimmed = name.trim();
+    if trimmed.is_empty()
+        || trimmed.len() > MAX_NAME_LEN
+        || trimmed.starts_with('.')
+        || !trimmed
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(RegistryError::InvalidName(name.to_string()));
+    }
+    Ok(trimmed)
+}
+
+fn check_config(name: &str, config: &AgentConfig) -> Result<(), RegistryError> {
+    // References are only checked once they are resolved.
+    if let Some(endpoint) = config.endpoint.as_deref().filter(|e| !e.contains("${")) {
+        let valid = Url::parse(endpoint)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host());
+        if !valid {
+            return Err(RegistryError::InvalidEndpoint(
+                name.to_string(),
+                format!("'{}' is not an http(s) URL", endpoint),
+            ));
+        }
+    }
+
+    if let Some(ssh) = &config.ssh {
+        let host = ssh.host.trim();
+        if host.is_empty() || host.starts_with('-') || host.contains(char::is_whitespace) {
+            return Err(RegistryError::InvalidSshTarget(
+                name.to_string(),
+                format!("invalid host '{}'", ssh.host),
+            ));
+        }
+        if ssh.user.is_empty() || ssh.user.starts_with('-') {
+            return Err(RegistryError::InvalidSshTarget(
+                name.to_string(),
+                format!("invalid user '{}'", ssh.user),
+            ));
+        }
+        if ssh.port == 0 {
+            return Err(RegistryError::InvalidSshTarget(
+                name.to_string(),
+                "port must not be 0".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_agents(registry: tauri::State<'_, AgentRegistry>) -> Vec<AgentInfo> {
+    registry.list()
+}
+
+#[tauri::command]
+pub fn get_agent(
+    registry: tauri::State<'_, AgentRegistry>,
+    name: String,
+) -> Result<AgentInfo, RegistryError> {
+    registry.get(&name)
+}
+
+#[tauri::command]
+pub fn create_agent(
+    registry: tauri::State<'_, AgentRegistry>,
+    agent: AgentInfo,
+) -> Result<(), RegistryError> {
+    registry.create(agent)
+}
+
+#[tauri::command]
+pub fn update_agent(
+    registry: tauri::State<'_, AgentRegistry>,
+    name: String,
+    agent: AgentInfo,
+) -> Result<(), RegistryError> {
+    registry.replace(&name, agent)
+}
+
+#[tauri::command]
+pub fn delete_agent(
+    registry: tauri::State<'_, AgentRegistry>,
+    name: String,
+) -> Result<(), RegistryError> {
+    registry.delete(&name)
+}
+
+#[tauri::command]
+pub fn import_agents(
+    workspace: tauri::State<'_, Workspace>,
+    registry: tauri::State<'_, AgentRegistry>,
+    file_path: String,
+    replace: bool,
+) -> Result<ImportSummary, RegistryError> {
+    let file_path = workspace.resolve_existing(&file_path)?;
+    let content = fs::read_to_string(&file_path).map_err(|e| WorkspaceError::io(&file_path, e))?;
+    let definition =
+        model::parse(&content).map_err(|e| RegistryError::InvalidDefinition(e.to_string()))?;
+    registry.import(&definition.agents.unwrap_or_default(), replace)
+}
@@ -0,0 +1,662 @@
+// Deploys agent packages to their hosts over SSH.
+//
+// Follows `src/agents/deployer.py`'s `AgentDeployer`: the package directory
+// that `aptcli agent create` generated is copied to the host, then installed
+// the way its deployment type needs (docker compose, a crontab entry, the
+// systemd install script or a background shell script), and the agent is
+// checked on its health endpoint. Every stage is reported as an
+// `agent-deploy-progress` event on the window that started the deploy.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+
+use crate::agent_client::{AgentClient, AgentError, AgentHttp};
+use crate::agents::{AgentRegistry, DeploymentType, RegistryError};
+use crate::packages::default_package_dir;
+use crate::profiles::{ProfileError, ProfileStore};
+use crate::ssh::{self, CommandOutput, RemoteShell, SshClient, SshError};
+use crate::variables::Environment;
+use crate::vault::Vault;
+use crate::workspace::Workspace;
+
+pub const DEPLOY_PROGRESS_EVENT: &str = "agent-deploy-progress";
+
+/// Where `AgentDeployer.deploy` installs agents.
+pub const DEFAULT_REMOTE_DIR: &str = "/opt/apt-agent";
+/// Port the generated packages run the agent server on by default.
+const AGENT_PORT: u16 = 9090;
+/// Image builds and `pip install` can take a while.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+const VERIFY_ATTEMPTS: u32 = 10;
+const VERIFY_DELAY: Duration = Duration::from_secs(3);
+/// Output kept in a failed command's error.
+const MAX_ERROR_OUTPUT: usize = 2000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeployError {
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+    #[error(transparent)]
+    Agent(#[from] AgentError),
+    #[error(transparent)]
+    Ssh(#[from] SshError),
+    #[error("agent '{0}' has no SSH target")]
+    NoSshTarget(String),
+    #[error("agent '{0}' has no deployment type")]
+    NoDeploymentType(String),
+    #[error("agent '{0}' is already being deployed")]
+    AlreadyDeploying(String),
+    #[error("cannot read agent package {0}: {1}")]
+    Package(String, String),
+    #[error("`{0}` failed: {1}")]
+    CommandFailed(String, String),
+    #[error("`{0}` did not finish in time")]
+    CommandTimeout(String),
+    #[error("{0} is not installed on the agent host")]
+    MissingTool(String),
+    #[error("agent '{0}' did not become healthy: {1}")]
+    Unhealthy(String, String),
+}
+
+impl DeployError {
+    fn kind(&self) -> &'static str {
+        match self {
+            DeployError::Registry(_) => "registry",
+            DeployError::Profile(_) => "profile",
+            DeployError::Agent(_) => "agent",
+            DeployError::Ssh(_) => "ssh",
+            DeployError::NoSshTarget(_) => "no_ssh_target",
+            DeployError::NoDeploymentType(_) => "no_deployment_type",
+            DeployError::AlreadyDeploying(_) => "already_deploying",
+            DeployError::Package(..) => "package",
+            DeployError::CommandFailed(..) => "command_failed",
+            DeployError::CommandTimeout(_) => "timeout",
+            DeployError::MissingTool(_) => "missing_tool",
+            DeployError::Unhealthy(..) => "unhealthy",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// agent name, the package path or the remote command.
+impl Serialize for DeployError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            DeployError::Registry(e) => return e.serialize(serializer),
+            DeployError::Profile(e) => return e.serialize(serializer),
+            DeployError::Agent(e) => return e.serialize(serializer),
+            DeployError::Ssh(e) => return e.serialize(serializer),
+            DeployError::NoSshTarget(path)
+            | DeployError::NoDeploymentType(path)
+            | DeployError::AlreadyDeploying(path)
+            | DeployError::Package(path, _)
+            | DeployError::CommandFailed(path, _)
+            | DeployError::CommandTimeout(path)
+            | DeployError::MissingTool(path)
+            | DeployError::Unhealthy(path, _) => Some(path.as_str()),
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployStage {
+    Connect,
+    Prepare,
+    Transfer,
+    Install,
+    Verify,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StageStatus {
+    Started,
+    Completed,
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DeployProgress {
+    pub agent: String,
+    pub stage: DeployStage,
+    pub status: StageStatus,
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct DeploySummary {
+    pub agent: String,
+    pub target: String,
+    pub remote_dir: String,
+    pub files: usize,
+    /// False for cron agents, which only start on their schedule.
+    pub verified: bool,
+}
+
+/// How the deployed agent is checked.
+pub enum HealthCheck {
+    /// Through its registered endpoint, from here.
+    Endpoint(AgentClient),
+    /// With curl on the agent host, for agents without an endpoint.
+    OnHost,
+}
+
+/// What to deploy where; the connection is made by the caller.
+pub struct Deployment {
+    pub agent: String,
+    /// `user@host:port`, for the summary.
+    pub target: String,
+    pub deployment: DeploymentType,
+    pub package_dir: PathBuf,
+    pub remote_dir: String,
+    /// Prefix `sudo` to privileged steps; not needed when logged in as root.
+    pub sudo: bool,
+    pub health_check: HealthCheck,
+}
+
+struct PackageFile {
+    relative: String,
+    content: Vec<u8>,
+    executable: bool,
+}
+
+/// Reports stage transitions; remembers the running stage so a failure can
+/// be attributed to it.
+struct Progress<'a, F: FnMut(DeployProgress)> {
+    agent: &'a str,
+    current: Option<DeployStage>,
+    emit: &'a mut F,
+}
+
+impl<F: FnMut(DeployProgress)> Progress<'_, F> {
+    fn send(&mut self, stage: DeployStage, status: StageStatus, message: String) {
+        (self.emit)(DeployProgress {
+            agent: self.agent.to_string(),
+            stage,
+            status,
+            message,
+            at: Utc::now(),
+        });
+    }
+
+    fn start(&mut self, stage: DeployStage, message: impl Into<String>) {
+        self.current = Some(stage);
+        self.send(stage, StageStatus::Started, message.into());
+    }
+
+    fn complete(&mut self, message: impl Into<String>) {
+        if let Some(stage) = self.current.take() {
+            self.send(stage, StageStatus::Completed, message.into());
+        }
+    }
+
+    fn fail(&mut self, error: &DeployError) {
+        if let Some(stage) = self.current.take() {
+            self.send(stage, StageStatus::Failed, error.to_string());
+        }
+    }
+}
+
+/// Agents with a deploy in flight; a second deploy of the same agent is
+/// refused rather than interleaved with the first.
+#[derive(Default)]
+pub struct Deployer {
+    active: Mutex<HashSet<String>>,
+}
+
+struct ActiveDeploy<'a> {
+    deployer: &'a Deployer,
+    agent: String,
+}
+
+impl Drop for ActiveDeploy<'_> {
+    fn drop(&mut self) {
+        self.deployer.active.lock().unwrap().remove(&self.agent);
+    }
+}
+
+impl Deployer {
+    fn begin(&self, agent: &str) -> Result<ActiveDeploy<'_>, DeployError> {
+        if !self.active.lock().unwrap().insert(agent.to_string()) {
+            return Err(DeployError::AlreadyDeploying(agent.to_string()));
+        }
+        Ok(ActiveDeploy {
+            deployer: self,
+            agent: agent.to_string(),
+        })
+    }
+}
+
+impl Deployment {
+    /// Runs every stage after connecting: prepare, transfer, install and
+    /// verify.
+    pub async fn run<S: RemoteShell>(
+        &self,
+        shell: &mut S,
+        emit: &mut impl FnMut(DeployProgress),
+    ) -> Result<DeploySummary, DeployError> {
+        let mut progress = Progress {
+            agent: &self.agent,
+            current: None,
+            emit,
+        };
+        let result = self.stages(shell, &mut progress).await;
+        if let Err(e) = &result {
+            progress.fail(e);
+        }
+        result
+    }
+
+    async fn stages<S: RemoteShell, F: FnMut(DeployProgress)>(
+        &self,
+        shell: &mut S,
+        progress: &mut Progress<'_, F>,
+    ) -> Result<DeploySummary, DeployError> {
+        let dir = ssh::quote(&self.remote_dir);
+
+        progress.start(
+            DeployStage::Prepare,
+            format!("Reading {}", self.package_dir.display()),
+        );
+        let files = package_files(&self.package_dir)?;
+        run(shell, &format!("mkdir -p {dir}")).await?;
+        progress.complete(format!("{} files to transfer", files.len()));
+
+        progress.start(
+            DeployStage::Transfer,
+            format!("Copying {} files to {}", files.len(), self.remote_dir),
+        );
+        for file in &files {
+            self.upload(shell, file).await?;
+        }
+        progress.complete(format!("Copied {} files", files.len()));
+
+        progress.start(
+            DeployStage::Install,
+            format!("Installing {} agent", deployment_name(self.deployment)),
+        );
+        let installed = match self.deployment {
+            DeploymentType::Docker => self.install_docker(shell).await?,
+            DeploymentType::Cron => self.install_cron(shell).await?,
+            DeploymentType::Systemd => self.install_systemd(shell).await?,
+            DeploymentType::Shell => self.install_shell(shell).await?,
+        };
+        progress.complete(installed);
+
+        let verified = if self.deployment == DeploymentType::Cron {
+            false
+        } else {
+            progress.start(
+                DeployStage::Verify,
+                "Waiting for the agent to report healthy",
+            );
+            let message = self.verify(shell).await?;
+            progress.complete(message);
+            true
+        };
+
+        Ok(DeploySummary {
+            agent: self.agent.clone(),
+            target: self.target.clone(),
+            remote_dir: self.remote_dir.clone(),
+            files: files.len(),
+            verified,
+        })
+    }
+
+    /// Writes one file through `cat`, so the host needs no SFTP subsystem.
+    async fn upload<S: RemoteShell>(
+        &self,
+        shell: &mut S,
+        file: &PackageFile,
+    ) -> Result<(), DeployError> {
+        let remote = format!(
+            "{}/{}",
+            self.remote_dir.trim_end_matches('/'),
+            file.relative
+        );
+        let parent = remote.rsplit_once('/').map_or(".", |(parent, _)| parent);
+        let content = self.localize(&file.content);
+        let mode = if file.executable { "755" } else { "644" };
+        run_with_input(
+            shell,
+            &format!(
+                "mkdir -p {} && cat > {} && chmod {mode} {}",
+                ssh::quote(parent),
+                ssh::quote(&remote),
+                ssh::quote(&remote)
+            ),
+            &content,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// The provisioner writes the absolute path of the local package into
+    /// the systemd unit and the crontab entry; on the host it is the remote
+    /// directory.
+    fn localize(&self, content: &[u8]) -> Vec<u8> {
+        let local = self.package_dir.to_string_lossy();
+        match std::str::from_utf8(content) {
+            Ok(text) if text.contains(local.as_ref()) => text
+                .replace(local.as_ref(), self.remote_dir.trim_end_matches('/'))
+                .into_bytes(),
+            _ => content.to_vec(),
+        }
+    }
+
+    async fn install_docker<S: RemoteShell>(&self, shell: &mut S) -> Result<String, DeployError> {
+        if !exec(shell, "command -v docker").await?.success() {
+            return Err(DeployError::MissingTool("docker".to_string()));
+        }
+        let compose = if exec(shell, "command -v docker-compose").await?.success() {
+            "docker-compose"
+        } else if exec(shell, "docker compose version").await?.success() {
+            "docker compose"
+        } else {
+            return Err(DeployError::MissingTool("docker compose".to_string()));
+        };
+
+        let dir = ssh::quote(&self.remote_dir);
+        let sudo = self.sudo_prefix();
+        run(shell, &format!("cd {dir} && {sudo}{compose} build")).await?;
+        run(shell, &format!("cd {dir} && {sudo}{compose} up -d")).await?;
+        Ok("Docker agent started".to_string())
+    }
+
+    async fn install_cron<S: RemoteShell>(&self, shell: &mut S) -> Result<String, DeployError> {
+        let dir = ssh::quote(&self.remote_dir);
+        run(shell, &format!("cd {dir} && python3 -m venv venv")).await?;
+        run(
+            shell,
+            &format!("cd {dir} && venv/bin/pip install -r requirements.txt"),
+        )
+        .await?;
+        // Entries of an earlier deploy are replaced, not duplicated.
+        run(
+            shell,
+            &format!(
+                "cd {dir} && (crontab -l 2>/dev/null | grep -vxF -f crontab.txt; cat crontab.txt) | crontab -"
+            ),
+        )
+        .await?;
+        Ok("Cron agent installed; it starts on its schedule".to_string())
+    }
+
+    async fn install_systemd<S: RemoteShell>(&self, shell: &mut S) -> Result<String, DeployError> {
+        let dir = ssh::quote(&self.remote_dir);
+        run(
+            shell,
+            &format!("cd {dir} && {}./install.sh", self.sudo_prefix()),
+        )
+        .await?;
+        Ok("Systemd service installed and started".to_string())
+    }
+
+    async fn install_shell<S: RemoteShell>(&self, shell: &mut S) -> Result<String, DeployError> {
+        let dir = ssh::quote(&self.remote_dir);
+        // Without closing stdin the session would wait for the agent to exit.
+        run(
+            shell,
+            &format!("cd {dir} && nohup ./start_agent.sh > agent.log 2>&1 < /dev/null &"),
+        )
+        .await?;
+        Ok("Shell agent started in the background".to_string())
+    }
+
+    async fn verify<S: RemoteShell>(&self, shell: &mut S) -> Result<String, DeployError> {
+        let mut last_error = String::new();
+        for attempt in 1..=VERIFY_ATTEMPTS {
+            tokio::time::sleep(VERIFY_DELAY).await;
+            let result = match &self.health_check {
+                HealthCheck::Endpoint(client) => match client.health().await {
+                    Ok(health) if health.status == "healthy" => Ok(()),
+                    Ok(health) => Err(format!("agent reports status '{}'", health.status)),
+                    Err(e) => Err(e.to_string()),
+                },
+                HealthCheck::OnHost => {
+                    let output = exec(
+                        shell,
+                        &format!("curl -s http://localhost:{AGENT_PORT}/health"),
+                    )
+                    .await?;
+                    if output.stdout.to_lowercase().contains("healthy") {
+                        Ok(())
+                    } else if output.success() {
+                        Err(format!("unexpected response: {}", output.stdout.trim()))
+                    } else {
+                        Err(format!("no answer on port {AGENT_PORT}"))
+                    }
+                }
+            };
+            match result {
+                Ok(()) => return Ok(format!("Agent is healthy (check {attempt})")),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(DeployError::Unhealthy(self.agent.clone(), last_error))
+    }
+
+    fn sudo_prefix(&self) -> &'static str {
+        if self.sudo {
+            // Never wait for a password prompt nobody can answer.
+            "sudo -n "
+        } else {
+            ""
+        }
+    }
+}
+
+fn deployment_name(deployment: DeploymentType) -> &'static str {
+    match deployment {
+        DeploymentType::Docker => "Docker",
+        DeploymentType::Cron => "cron",
+        DeploymentType::Systemd => "systemd",
+        DeploymentType::Shell => "shell",
+    }
+}
+
+/// Every file below `dir`, with `/`-separated relative paths, sorted.
+fn package_files(dir: &Path) -> Result<Vec<PackageFile>, DeployError> {
+    fn walk(dir: &Path, prefix: &str, files: &mut Vec<PackageFile>) -> std::io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            let metadata = fs::metadata(&path)?;
+            if metadata.is_dir() {
+                walk(&path, &relative, files)?;
+            } else if metadata.is_file() {
+                files.push(PackageFile {
+                    relative,
+                    content: fs::read(&path)?,
+                    executable: is_executable(&metadata),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    let package_error =
+        |e: std::io::Error| DeployError::Package(dir.display().to_string(), e.to_string());
+    if !dir.is_dir() {
+        return Err(DeployError::Package(
+            dir.display().to_string(),
+            "not a directory; create the agent with `aptcli agent create` first".to_string(),
+        ));
+    }
+    let mut files = Vec::new();
+    walk(dir, "", &mut files).map_err(package_error)?;
+    files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(files)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+// Windows has no execute bit; the generated scripts are the ones that need it.
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+async fn exec<S: RemoteShell>(shell: &mut S, command: &str) -> Result<CommandOutput, DeployError> {
+    exec_with_input(shell, command, &[]).await
+}
+
+async fn exec_with_input<S: RemoteShell>(
+    shell: &mut S,
+    command: &str,
+    stdin: &[u8],
+) -> Result<CommandOutput, DeployError> {
+    match tokio::time::timeout(COMMAND_TIMEOUT, shell.exec(command, stdin)).await {
+        Ok(output) => Ok(output?),
+        Err(_) => Err(DeployError::CommandTimeout(command.to_string())),
+    }
+}
+
+async fn run<S: RemoteShell>(shell: &mut S, command: &str) -> Result<CommandOutput, DeployError> {
+    run_with_input(shell, command, &[]).await
+}
+
+/// Like `exec_with_input`, but a non-zero exit status is an error.
+async fn run_with_input<S: RemoteShell>(
+    shell: &mut S,
+    command: &str,
+    stdin: &[u8],
+) -> Result<CommandOutput, DeployError> {
+    let output = exec_with_input(shell, command, stdin).await?;
+    if output.success() {
+        return Ok(output);
+    }
+
+    let status = match output.exit_status {
+        Some(status) => format!("exit status {status}"),
+        None => "killed by a signal".to_string(),
+    };
+    let detail = if output.stderr.trim().is_empty() {
+        output.stdout.trim()
+    } else {
+        output.stderr.trim()
+    };
+    // The end of the output is where the cause usually is.
+    let start = detail.len().saturating_sub(MAX_ERROR_OUTPUT);
+    let start = (start..detail.len())
+        .find(|&i| detail.is_char_boundary(i))
+        .unwrap_or(detail.len());
+    Err(DeployError::CommandFailed(
+        command.to_string(),
+        if detail.is_empty() {
+            status
+        } else {
+            format!("{status}: {}", &detail[start..])
+        },
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn deploy_agent(
+    window: tauri::Window,
+    deployer: tauri::State<'_, Deployer>,
+    ssh: tauri::State<'_, SshClient>,
+    registry: tauri::State<'_, AgentRegistry>,
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    name: String,
+    package_dir: Option<PathBuf>,
+    remote_dir: Option<String>,
+) -> Result<DeploySummary, DeployError> {
+    let agent = registry.get(&name)?;
+    let target = agent
+        .config
+        .ssh
+        .clone()
+        .ok_or_else(|| DeployError::NoSshTarget(name.clone()))?;
+    let deployment = agent
+        .config
+        .deployment
+        .ok_or_else(|| DeployError::NoDeploymentType(name.clone()))?;
+    let package_dir = package_dir
+        .or_else(|| default_package_dir(&name))
+        .ok_or_else(|| DeployError::Package(name.clone(), "no home directory".to_string()))?;
+    // Resolved up front, so a locked vault or an unset variable fails the
+    // deploy before anything changes on the host.
+    let health_check = match agent.config.endpoint {
+        Some(_) => {
+            let environment = Environment::load(&workspace, &profiles, None, None)?;
+            HealthCheck::Endpoint(agent.client(&http, &vault, &environment)?)
+        }
+        None => HealthCheck::OnHost,
+    };
+    let _active = deployer.begin(&name)?;
+
+    let deployment = Deployment {
+        agent: name.clone(),
+        target: ssh::display_target(&target),
+        deployment,
+        package_dir,
+        remote_dir: remote_dir.unwrap_or_else(|| DEFAULT_REMOTE_DIR.to_string()),
+        sudo: target.user != "root",
+        health_check,
+    };
+    let mut emit = |progress: DeployProgress| {
+        let _ = window.emit(DEPLOY_PROGRESS_EVENT, progress);
+    };
+
+    let mut progress = Progress {
+        agent: &name,
+        current: None,
+        emit: &mut emit,
+    };
+    progress.start(
+        DeployStage::Connect,
+        format!("Connecting to {}", deployment.target),
+    );
+    let mut session = match ssh.connect(&target).await {
+        Ok(session) => session,
+        Err(e) => {
+            let e = DeployError::from(e);
+            progress.fail(&e);
+            return Err(e);
+        }
+    };
+    progress.complete(format!("Connected to {}", deployment.target));
+
+    let result = deployment.run(&mut session, &mut emit).await;
+    session.close().await;
+    result
+}
@@ -0,0 +1,169 @@
+// Recursive discovery of unified YAML test definitions.
+//
+// Files are found with the same rules as `git status` (`.gitignore`, hidden
+// files) and only parsed far enough to read `test_info` and to tell which
+// top-level sections are present, so the test picker can show suite names.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+
+use crate::workspace::{Workspace, WorkspaceError};
+
+// Generated output and dependencies can hold thousands of YAML files that
+// are never test definitions.
+const SKIPPED_DIRS: &[&str] = &["performance_results", "node_modules", "target"];
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+#[derive(Clone, Serialize)]
+pub struct TestDefinitionSummary {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub test_suite_name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub sections: Vec<&'static str>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DefinitionHeader {
+    test_info: Option<TestInfoHeader>,
+    k6_tests: Option<IgnoredAny>,
+    jmeter_tests: Option<IgnoredAny>,
+    workflows: Option<IgnoredAny>,
+    agents: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+struct TestInfoHeader {
+    test_suite_name: Option<String>,
+    description: Option<String>,
+    // Unquoted versions such as `version: 2.0` parse as numbers.
+    version: Option<serde_yaml::Value>,
+}
+
+/// Scans `root` recursively and summarizes every test definition below it.
+pub fn discover(root: &Path) -> Vec<TestDefinitionSummary> {
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .filter_entry(|entry| {
+            !(entry.file_type().is_some_and(|t| t.is_dir())
+                && SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+        })
+        .build();
+
+    let mut definitions: Vec<TestDefinitionSummary> = walker
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter(|entry| is_yaml(entry.path()))
+        .filter_map(|entry| summarize(root, entry.path()))
+        .collect();
+
+    definitions.sort_by(|a, b| a.path.cmp(&b.path));
+    definitions
+}
+
+pub fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("yml" | "yaml")
+    )
+}
+
+/// Reads the header of a YAML file, returning `None` for files that are
+/// valid YAML but not test definitions (compose files, CI configs, ...).
+fn summarize(root: &Path, path: &Path) -> Option<TestDefinitionSummary> {
+    let mut summary = TestDefinitionSummary {
+        path: path.to_path_buf(),
+        relative_path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+        test_suite_name: None,
+        description: None,
+        version: None,
+        sections: Vec::new(),
+        error: None,
+    };
+
+    let content = match fs::metadata(path) {
+        Ok(meta) if meta.len() > MAX_FILE_SIZE => {
+            summary.error = Some(format!("file is larger than {} bytes", MAX_FILE_SIZE));
+            return Some(summary);
+        }
+        Ok(_) => fs::read_to_string(path),
+        Err(e) => Err(e),
+    };
+    let content = match content {
+        Ok(content) => content,
+        Err(e) => {
+            summary.error = Some(e.to_string());
+            return Some(summary);
+        }
+    };
+
+    let header: DefinitionHeader = match serde_yaml::from_str(&content) {
+        Ok(header) => header,
+        Err(e) => {
+            // Only report broken files that at least look like definitions.
+            if !content.contains("test_info:") {
+                return None;
+            }
+            summary.error = Some(e.to_string());
+            return Some(summary);
+        }
+    };
+
+    for (name, present) in [
+        ("k6_tests", header.k6_tests.is_some()),
+        ("jmeter_tests", header.jmeter_tests.is_some()),
+        ("workflows", header.workflows.is_some()),
+        ("agents", header.agents.is_some()),
+    ] {
+        if present {
+            summary.sections.push(name);
+        }
+    }
+
+    if header.test_info.is_none() && summary.sections.is_empty() {
+        return None;
+    }
+
+    if let Some(info) = header.test_info {
+        summary.test_suite_name = info.test_suite_name;
+        summary.description = info.description;
+        summary.version = info.version.and_then(|version| match version {
+            serde_yaml::Value::String(s) => Some(s),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        });
+    }
+
+    Some(summary)
+}
+
+/// Lists the test definitions below `directory`, or below every workspace
+/// root when no directory is given.
+#[tauri::command]
+pub async fn discover_test_definitions(
+    workspace: tauri::State<'_, Workspace>,
+    directory: Option<String>,
+) -> Result<Vec<TestDefinitionSummary>, WorkspaceError> {
+    let roots = match directory {
+        Some(directory) => vec![workspace.resolve_existing(&directory)?],
+        None => workspace
+            .roots()
+            .into_iter()
+            .map(|root| root.path)
+            .collect(),
+    };
+
+    let mut definitions: Vec<TestDefinitionSummary> =
+        roots.iter().flat_map(|root| discover(root)).collect();
+    // Roots may be nested in one another.
+    definitions.sort_by(|a, b| a.path.cmp(&b.path));
+    definitions.dedup_by(|a, b| a.path == b.path);
+
+    Ok(definitions)
+}
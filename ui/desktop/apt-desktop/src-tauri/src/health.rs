@@ -0,0 +1,421 @@
+// Background health checks of the registered agents.
+//
+// Works like `src/agents/health_monitor.py`'s `AgentHealthMonitor`: every
+// agent in the registry is polled on `/health` (and `/stats`, for the job
+// counts) on an interval, and is only marked unhealthy after several
+// consecutive failed checks. Unhealthy agents are polled less and less
+// often. Status transitions are emitted as `agent-status-changed` events.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+
+use crate::agent_client::{AgentClient, AgentHealth, AgentHttp, AgentStats};
+use crate::agents::{AgentInfo, AgentMode, AgentRegistry};
+use crate::profiles::ProfileStore;
+use crate::variables::Environment;
+use crate::vault::Vault;
+use crate::versions;
+use crate::workspace::{Workspace, WorkspaceError};
+
+pub const AGENT_STATUS_EVENT: &str = "agent-status-changed";
+
+const SETTINGS_FILE: &str = "health_monitor.json";
+/// A check gives up well before the next one is due.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_SAMPLES: usize = 120;
+const MIN_INTERVAL_SECS: u64 = 5;
+/// Backoff doubles the interval at most this many times.
+const MAX_BACKOFF_DOUBLINGS: u32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Unhealthy,
+    /// Not checked yet, or cannot be checked: no endpoint, an unset
+    /// variable or a locked vault.
+    Unknown,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MonitorSettings {
+    /// Seconds between checks; an agent's `health_check_interval` takes
+    /// precedence.
+    pub interval_secs: u64,
+    /// Consecutive failed checks before an agent is unhealthy.
+    pub failure_threshold: u32,
+    /// Upper bound of the backoff for unhealthy agents, in seconds.
+    pub max_backoff_secs: u64,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> MonitorSettings {
+        MonitorSettings {
+            interval_secs: 30,
+            failure_threshold: 3,
+            max_backoff_secs: 300,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct HealthSample {
+    pub checked_at: DateTime<Utc>,
+    pub healthy: bool,
+    /// Round trip of the `/health` request; `None` when it failed.
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AgentHealthState {
+    pub agent: String,
+    pub status: HealthStatus,
+    pub last_check: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+    pub total_checks: u64,
+    pub total_failures: u64,
+    /// Share of successful checks since the app started, in percent.
+    pub uptime_percentage: f64,
+    pub last_error: Option<String>,
+    pub health: Option<AgentHealth>,
+    pub stats: Option<AgentStats>,
+    /// The most recent checks, oldest first.
+    pub history: VecDeque<HealthSample>,
+    #[serde(skip)]
+    next_check: Option<Instant>,
+}
+
+impl AgentHealthState {
+    fn new(agent: &str) -> AgentHealthState {
+        AgentHealthState {
+            agent: agent.to_string(),
+            status: HealthStatus::Unknown,
+            last_check: None,
+            last_success: None,
+            consecutive_failures: 0,
+            total_checks: 0,
+            total_failures: 0,
+            uptime_percentage: 0.0,
+            last_error: None,
+            health: None,
+            stats: None,
+            history: VecDeque::new(),
+            next_check: None,
+        }
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        self.next_check.is_none_or(|next| next <= now)
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct AgentStatusChange {
+    pub agent: String,
+    pub previous: HealthStatus,
+    pub status: HealthStatus,
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// What a single check found out.
+enum CheckOutcome {
+    Healthy {
+        health: Box<AgentHealth>,
+        stats: Option<AgentStats>,
+        latency: Duration,
+    },
+    Failed(String),
+    /// The agent could not be checked at all.
+    Unresolved(String),
+}
+
+pub struct HealthMonitor {
+    settings: RwLock<MonitorSettings>,
+    settings_path: PathBuf,
+    states: Mutex<HashMap<String, AgentHealthState>>,
+    wake: Notify,
+}
+
+impl HealthMonitor {
+    pub fn load(data_dir: &Path) -> HealthMonitor {
+        let settings_path = data_dir.join(SETTINGS_FILE);
+        let settings = fs::read_to_string(&settings_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        HealthMonitor {
+            settings: RwLock::new(settings),
+            settings_path,
+            states: Mutex::new(HashMap::new()),
+            wake: Notify::new(),
+        }
+    }
+
+    pub fn settings(&self) -> MonitorSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: MonitorSettings) -> Result<(), WorkspaceError> {
+        let settings = MonitorSettings {
+            interval_secs: settings.interval_secs.max(MIN_INTERVAL_SECS),
+            failure_threshold: settings.failure_threshold.max(1),
+            max_backoff_secs: settings.max_backoff_secs.max(MIN_INTERVAL_SECS),
+        };
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| WorkspaceError::InvalidPath(e.to_string()))?;
+        if let Some(dir) = self.settings_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| WorkspaceError::io(dir, e))?;
+        }
+        versions::atomic_write(&self.settings_path, content.as_bytes())?;
+
+        *self.settings.write().unwrap() = settings;
+        self.check_now();
+        Ok(())
+    }
+
+    /// Current state of every registered agent, in registry order.
+    pub fn states(&self, registry: &AgentRegistry) -> Vec<AgentHealthState> {
+        let states = self.states.lock().unwrap();
+        registry
+            .list()
+            .iter()
+            .map(|agent| {
+                states
+                    .get(&agent.name)
+                    .cloned()
+                    .unwrap_or_else(|| AgentHealthState::new(&agent.name))
+            })
+            .collect()
+    }
+
+    /// Checks every agent right away instead of when it is next due.
+    pub fn check_now(&self) {
+        for state in self.states.lock().unwrap().values_mut() {
+            state.next_check = None;
+        }
+        self.wake.notify_one();
+    }
+
+    /// Agents whose check is due. States of agents that are no longer
+    /// registered are dropped.
+    fn due(&self, agents: &[AgentInfo], now: Instant) -> Vec<AgentInfo> {
+        let mut states = self.states.lock().unwrap();
+        states.retain(|name, _| agents.iter().any(|agent| &agent.name == name));
+        agents
+            .iter()
+            .filter(|agent| states.get(&agent.name).is_none_or(|s| s.is_due(now)))
+            .cloned()
+            .collect()
+    }
+
+    /// Time until the next agent is due, at most one interval.
+    fn next_wakeup(&self, now: Instant) -> Duration {
+        let interval = Duration::from_secs(self.settings().interval_secs);
+        self.states
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|state| state.next_check)
+            .map(|next| next.saturating_duration_since(now))
+            .min()
+            .map_or(interval, |wait| wait.min(interval))
+    }
+
+    /// Records the outcome of a check; returns the transition, if any.
+    fn record(&self, agent: &AgentInfo, outcome: CheckOutcome) -> Option<AgentStatusChange> {
+        let settings = self.settings();
+        let now = Utc::now();
+        let mut states = self.states.lock().unwrap();
+        let state = states
+            .entry(agent.name.clone())
+            .or_insert_with(|| AgentHealthState::new(&agent.name));
+        let previous = state.status;
+
+        let interval = agent
+            .config
+            .health_check_interval
+            .unwrap_or(settings.interval_secs)
+            .max(MIN_INTERVAL_SECS);
+        let mut delay = Duration::from_secs(interval);
+
+        match outcome {
+            CheckOutcome::Healthy {
+                health,
+                stats,
+                latency,
+            } => {
+                state.status = HealthStatus::Healthy;
+                state.last_check = Some(now);
+                state.last_success = Some(now);
+                state.consecutive_failures = 0;
+                state.total_checks += 1;
+                state.last_error = None;
+                state.health = Some(*health);
+                state.stats = stats;
+                push_sample(state, now, Some(latency));
+            }
+            CheckOutcome::Failed(error) => {
+                state.last_check = Some(now);
+                state.consecutive_failures += 1;
+                state.total_checks += 1;
+                state.total_failures += 1;
+                state.last_error = Some(error);
+                push_sample(state, now, None);
+                // A single failed check does not flip a healthy agent.
+                if state.consecutive_failures >= settings.failure_threshold {
+                    state.status = HealthStatus::Unhealthy;
+                    let doublings = (state.consecutive_failures - settings.failure_threshold + 1)
+                        .min(MAX_BACKOFF_DOUBLINGS);
+                    delay = (delay * 2u32.pow(doublings))
+                        .min(Duration::from_secs(settings.max_backoff_secs).max(delay));
+                }
+            }
+            CheckOutcome::Unresolved(error) => {
+                state.status = HealthStatus::Unknown;
+                state.consecutive_failures = 0;
+                state.last_error = Some(error);
+                state.health = None;
+                state.stats = None;
+            }
+        }
+
+        if state.total_checks > 0 {
+            let successful = state.total_checks - state.total_failures;
+            state.uptime_percentage = successful as f64 / state.total_checks as f64 * 100.0;
+        }
+        state.next_check = Some(Instant::now() + delay);
+
+        (state.status != previous).then(|| AgentStatusChange {
+            agent: agent.name.clone(),
+            previous,
+            status: state.status,
+            error: state.last_error.clone(),
+            at: now,
+        })
+    }
+}
+
+fn push_sample(state: &mut AgentHealthState, checked_at: DateTime<Utc>, latency: Option<Duration>) {
+    if state.history.len() == MAX_SAMPLES {
+        state.history.pop_front();
+    }
+    state.history.push_back(HealthSample {
+        checked_at,
+        healthy: latency.is_some(),
+        latency_ms: latency.map(|latency| latency.as_millis() as u64),
+    });
+}
+
+/// Starts the monitor loop; it runs for the lifetime of the app.
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check_due_agents(&app).await;
+            let monitor = app.state::<HealthMonitor>();
+            let wait = monitor.next_wakeup(Instant::now());
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = monitor.wake.notified() => {}
+            }
+        }
+    });
+}
+
+async fn check_due_agents(app: &AppHandle) {
+    let monitor = app.state::<HealthMonitor>();
+    let agents = app.state::<AgentRegistry>().list();
+    let due = monitor.due(&agents, Instant::now());
+    if due.is_empty() {
+        return;
+    }
+
+    // Endpoints and tokens are resolved like for a run without a test file.
+    let environment = Environment::load(
+        &app.state::<Workspace>(),
+        &app.state::<ProfileStore>(),
+        None,
+        None,
+    );
+
+    let mut checks = JoinSet::new();
+    for agent in due {
+        let client = match &environment {
+            Ok(_) if agent.config.mode == AgentMode::Emit => {
+                Err("agents in emit mode do not serve a health endpoint".to_string())
+            }
+            Ok(environment) => agent
+                .client(
+                    &app.state::<AgentHttp>(),
+                    &app.state::<Vault>(),
+                    environment,
+                )
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        checks.spawn(async move {
+            let outcome = match client {
+                Ok(client) => check(client.with_timeout(CHECK_TIMEOUT)).await,
+                Err(error) => CheckOutcome::Unresolved(error),
+            };
+            (agent, outcome)
+        });
+    }
+
+    while let Some(Ok((agent, outcome))) = checks.join_next().await {
+        if let Some(change) = monitor.record(&agent, outcome) {
+            let _ = app.emit_all(AGENT_STATUS_EVENT, change);
+        }
+    }
+}
+
+async fn check(client: AgentClient) -> CheckOutcome {
+    let started = Instant::now();
+    match client.health().await {
+        // Only the async agent server has `/stats`.
+        Ok(health) if health.status == "healthy" => CheckOutcome::Healthy {
+            latency: started.elapsed(),
+            stats: client.stats().await.ok(),
+            health: Box::new(health),
+        },
+        Ok(health) => CheckOutcome::Failed(format!("agent reports status '{}'", health.status)),
+        Err(e) => CheckOutcome::Failed(e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_agent_health_states(
+    monitor: tauri::State<'_, HealthMonitor>,
+    registry: tauri::State<'_, AgentRegistry>,
+) -> Vec<AgentHealthState> {
+    monitor.states(&registry)
+}
+
+#[tauri::command]
+pub fn check_agent_health_now(monitor: tauri::State<'_, HealthMonitor>) {
+    monitor.check_now();
+}
+
+#[tauri::command]
+pub fn get_health_monitor_settings(monitor: tauri::State<'_, HealthMonitor>) -> MonitorSettings {
+    monitor.settings()
+}
+
+#[tauri::command]
+pub fn set_health_monitor_settings(
+    monitor: tauri::State<'_, HealthMonitor>,
+    settings: MonitorSettings,
+) -> Result<(), WorkspaceError> {
+    monitor.set_settings(settings)
+}
@@ -0,0 +1,528 @@
+// Persistent history of pytest/aptcli runs.
+//
+// Runs are stored in an SQLite database under the app data directory; the
+// combined output of each run is kept next to it in `runs/<run_id>.log` so the
+// Results page can show past runs after a restart.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::junit::TestSummary;
+use crate::runner::RunResult;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+// Each entry upgrades the schema by one version; `PRAGMA user_version` records
+// how many have been applied.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE runs (
+        run_id TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        test_file TEXT,
+        arguments TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        finished_at TEXT NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        exit_code INTEGER,
+        signal INTEGER,
+        success INTEGER NOT NULL,
+        cancelled INTEGER NOT NULL,
+        output_path TEXT,
+        tests_total INTEGER,
+        tests_passed INTEGER,
+        tests_failed INTEGER,
+        tests_errors INTEGER,
+        tests_skipped INTEGER,
+        tests_duration_secs REAL
+    );
+    CREATE INDEX runs_started_at ON runs (started_at);
+    CREATE INDEX runs_test_file ON runs (test_file);
+",
+    "
+    ALTER TABLE runs ADD COLUMN profile TEXT;
+",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunKind {
+    Pytest,
+    Aptcli,
+}
+
+impl RunKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            RunKind::Pytest => "pytest",
+            RunKind::Aptcli => "aptcli",
+        }
+    }
+
+    fn parse(value: &str) -> RunKind {
+        match value {
+            "aptcli" => RunKind::Aptcli,
+            _ => RunKind::Pytest,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatusFilter {
+    Success,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HistoryEntry {
+    pub run_id: String,
+    pub kind: RunKind,
+    pub test_file: Option<String>,
+    /// Environment profile the run was started with.
+    pub profile: Option<String>,
+    pub arguments: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub success: bool,
+    pub cancelled: bool,
+    pub output_path: Option<String>,
+    pub tests: Option<TestSummary>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub test_file: Option<String>,
+    pub profile: Option<String>,
+    pub kind: Option<RunKind>,
+    pub status: Option<RunStatusFilter>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryPage {
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+    pub runs: Vec<HistoryEntry>,
+}
+
+#[derive(Default, Serialize)]
+pub struct RunStatistics {
+    pub total_runs: u64,
+    pub successful_runs: u64,
+    pub failed_runs: u64,
+    pub cancelled_runs: u64,
+    pub tests_passed: u64,
+    pub tests_failed: u64,
+    pub average_duration_ms: f64,
+}
+
+pub struct RunHistory {
+    conn: Mutex<Connection>,
+    output_dir: PathBuf,
+}
+
+impl RunHistory {
+    pub fn open(data_dir: &Path) -> Result<RunHistory, String> {
+        let output_dir = data_dir.join("runs");
+        fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+
+        let conn = Connection::open(data_dir.join("history.db")).map_err(|e| e.to_string())?;
+        migrate(&conn).map_err(|e| e.to_string())?;
+
+        Ok(RunHistory {
+            conn: Mutex::new(conn),
+            output_dir,
+        })
+    }
+
+    /// Stores a finished run together with its combined output.
+    pub fn record(
+        &self,
+        kind: RunKind,
+        test_file: Option<&str>,
+        profile: Option<&str>,
+        arguments: &[String],
+        result: &RunResult,
+    ) -> Result<HistoryEntry, String> {
+        let output_path = self.output_dir.join(format!("{}.log", result.run_id));
+        let output_path = match fs::write(&output_path, combined_output(result)) {
+            Ok(()) => Some(output_path.to_string_lossy().to_string()),
+            Err(_) => None,
+        };
+
+        let entry = HistoryEntry {
+            run_id: result.run_id.clone(),
+            kind,
+            test_file: test_file.map(str::to_string),
+            profile: profile.map(str::to_string),
+            arguments: arguments.to_vec(),
+            started_at: result.started_at,
+            finished_at: result.finished_at,
+            duration_ms: result.duration_ms,
+            exit_code: result.exit_code,
+            signal: result.signal,
+            success: result.success,
+            cancelled: result.cancelled,
+            output_path,
+            tests: result.tests.as_ref().map(|report| report.summary.clone()),
+        };
+
+        let tests = entry.tests.as_ref();
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO runs (
+                    run_id, kind, test_file, arguments, started_at, finished_at,
+                    duration_ms, exit_code, signal, success, cancelled, output_path,
+                    tests_total, tests_passed, tests_failed, tests_errors,
+                    tests_skipped, tests_duration_secs, profile
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+                params![
+                    entry.run_id,
+                    entry.kind.as_str(),
+                    entry.test_file,
+                    serde_json::to_string(&entry.arguments).map_err(|e| e.to_string())?,
+                    timestamp(&entry.started_at),
+                    timestamp(&entry.finished_at),
+                    entry.duration_ms as i64,
+                    entry.exit_code,
+                    entry.signal,
+                    entry.success,
+                    entry.cancelled,
+                    entry.output_path,
+                    tests.map(|t| t.total as i64),
+                    tests.map(|t| t.passed as i64),
+                    tests.map(|t| t.failed as i64),
+                    tests.map(|t| t.errors as i64),
+                    tests.map(|t| t.skipped as i64),
+                    tests.map(|t| t.duration_secs),
+                    entry.profile,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(entry)
+    }
+
+    pub fn query(&self, query: &HistoryQuery) -> Result<HistoryPage, String> {
+        let (filter, args) = where_clause(query);
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let offset = query.offset.unwrap_or(0);
+
+        let conn = self.conn.lock().unwrap();
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM runs {}", filter),
+                params_from_iter(args.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut page_args = args;
+        page_args.push(Value::Integer(limit as i64));
+        page_args.push(Value::Integer(offset as i64));
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT * FROM runs {} ORDER BY started_at DESC LIMIT ? OFFSET ?",
+                filter
+            ))
+            .map_err(|e| e.to_string())?;
+        let runs = stmt
+            .query_map(params_from_iter(page_args.iter()), entry_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(HistoryPage {
+            total: total as u64,
+            offset,
+            limit,
+            runs,
+        })
+    }
+
+    pub fn get(&self, run_id: &str) -> Result<Option<HistoryEntry>, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT * FROM runs WHERE run_id = ?1",
+                params![run_id],
+                entry_from_row,
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn read_output(&self, run_id: &str) -> Result<String, String> {
+        let entry = self
+            .get(run_id)?
+            .ok_or_else(|| format!("unknown run {}", run_id))?;
+        let path = entry
+            .output_path
+            .ok_or_else(|| format!("no output was saved for run {}", run_id))?;
+        fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+
+    /// Deletes the given runs and their output files, returning how many were
+    /// removed.
+    pub fn delete(&self, run_ids: &[String]) -> Result<usize, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut deleted = 0;
+
+        for run_id in run_ids {
+            let output_path: Option<Option<String>> = conn
+                .query_row(
+                    "SELECT output_path FROM runs WHERE run_id = ?1",
+                    params![run_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+
+            if let Some(path) = output_path.flatten() {
+                let _ = fs::remove_file(path);
+            }
+
+            deleted += conn
+                .execute("DELETE FROM runs WHERE run_id = ?1", params![run_id])
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes every run that started before `before`, or all runs if no
+    /// cutoff is given.
+    pub fn clear(&self, before: Option<DateTime<Utc>>) -> Result<usize, String> {
+        let run_ids: Vec<String> = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn
+                .prepare("SELECT run_id FROM runs WHERE ?1 IS NULL OR started_at < ?1")
+                .map_err(|e| e.to_string())?;
+            let ids = stmt
+                .query_map(params![before.as_ref().map(timestamp)], |row| row.get(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            ids
+        };
+
+        self.delete(&run_ids)
+    }
+
+    pub fn statistics(&self, query: &HistoryQuery) -> Result<RunStatistics, String> {
+        let (filter, args) = where_clause(query);
+
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!(
+                    "SELECT
+                        COUNT(*),
+                        COALESCE(SUM(success), 0),
+                        COALESCE(SUM(NOT success AND NOT cancelled), 0),
+                        COALESCE(SUM(cancelled), 0),
+                        COALESCE(SUM(tests_passed), 0),
+                        COALESCE(SUM(tests_failed + tests_errors), 0),
+                        COALESCE(AVG(duration_ms), 0)
+                    FROM runs {}",
+                    filter
+                ),
+                params_from_iter(args.iter()),
+                |row| {
+                    Ok(RunStatistics {
+                        total_runs: row.get::<_, i64>(0)? as u64,
+                        successful_runs: row.get::<_, i64>(1)? as u64,
+                        failed_runs: row.get::<_, i64>(2)? as u64,
+                        cancelled_runs: row.get::<_, i64>(3)? as u64,
+                        tests_passed: row.get::<_, i64>(4)? as u64,
+                        tests_failed: row.get::<_, i64>(5)? as u64,
+                        average_duration_ms: row.get(6)?,
+                    })
+                },
+            )
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[tauri::command]
+pub fn query_run_history(
+    history: tauri::State<'_, RunHistory>,
+    query: Option<HistoryQuery>,
+) -> Result<HistoryPage, String> {
+    history.query(&query.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn get_run_history_entry(
+    history: tauri::State<'_, RunHistory>,
+    run_id: String,
+) -> Result<Option<HistoryEntry>, String> {
+    history.get(&run_id)
+}
+
+#[tauri::command]
+pub fn read_run_output(
+    history: tauri::State<'_, RunHistory>,
+    run_id: String,
+) -> Result<String, String> {
+    history.read_output(&run_id)
+}
+
+#[tauri::command]
+pub fn delete_run_history(
+    history: tauri::State<'_, RunHistory>,
+    run_ids: Vec<String>,
+) -> Result<usize, String> {
+    history.delete(&run_ids)
+}
+
+#[tauri::command]
+pub fn clear_run_history(
+    history: tauri::State<'_, RunHistory>,
+    before: Option<DateTime<Utc>>,
+) -> Result<usize, String> {
+    history.clear(before)
+}
+
+#[tauri::command]
+pub fn get_run_statistics(
+    history: tauri::State<'_, RunHistory>,
+    query: Option<HistoryQuery>,
+) -> Result<RunStatistics, String> {
+    history.statistics(&query.unwrap_or_default())
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration,
+            index + 1
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn where_clause(query: &HistoryQuery) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut args = Vec::new();
+
+    if let Some(test_file) = &query.test_file {
+        conditions.push("test_file = ?");
+        args.push(Value::Text(test_file.clone()));
+    }
+    if let Some(profile) = &query.profile {
+        conditions.push("profile = ?");
+        args.push(Value::Text(profile.clone()));
+    }
+    if let Some(kind) = query.kind {
+        conditions.push("kind = ?");
+        args.push(Value::Text(kind.as_str().to_string()));
+    }
+    if let Some(status) = query.status {
+        conditions.push(match status {
+            RunStatusFilter::Success => "success = 1",
+            RunStatusFilter::Failed => "success = 0 AND cancelled = 0",
+            RunStatusFilter::Cancelled => "cancelled = 1",
+        });
+    }
+    if let Some(since) = &query.since {
+        conditions.push("started_at >= ?");
+        args.push(Value::Text(timestamp(since)));
+    }
+    if let Some(until) = &query.until {
+        conditions.push("started_at < ?");
+        args.push(Value::Text(timestamp(until)));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), args)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), args)
+    }
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    let arguments: String = row.get("arguments")?;
+    let tests_total: Option<i64> = row.get("tests_total")?;
+
+    let tests = match tests_total {
+        Some(total) => Some(TestSummary {
+            total: total as usize,
+            passed: row.get::<_, Option<i64>>("tests_passed")?.unwrap_or(0) as usize,
+            failed: row.get::<_, Option<i64>>("tests_failed")?.unwrap_or(0) as usize,
+            errors: row.get::<_, Option<i64>>("tests_errors")?.unwrap_or(0) as usize,
+            skipped: row.get::<_, Option<i64>>("tests_skipped")?.unwrap_or(0) as usize,
+            duration_secs: row
+                .get::<_, Option<f64>>("tests_duration_secs")?
+                .unwrap_or(0.0),
+        }),
+        None => None,
+    };
+
+    Ok(HistoryEntry {
+        run_id: row.get("run_id")?,
+        kind: RunKind::parse(&row.get::<_, String>("kind")?),
+        test_file: row.get("test_file")?,
+        profile: row.get("profile")?,
+        arguments: serde_json::from_str(&arguments).unwrap_or_default(),
+        started_at: parse_timestamp(&row.get::<_, String>("started_at")?),
+        finished_at: parse_timestamp(&row.get::<_, String>("finished_at")?),
+        duration_ms: row.get::<_, i64>("duration_ms")? as u64,
+        exit_code: row.get("exit_code")?,
+        signal: row.get("signal")?,
+        success: row.get("success")?,
+        cancelled: row.get("cancelled")?,
+        output_path: row.get("output_path")?,
+        tests,
+    })
+}
+
+// Fixed-width UTC timestamps sort lexically, which the range filters and the
+// ORDER BY rely on.
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn parse_timestamp(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_default()
+}
+
+fn combined_output(result: &RunResult) -> String {
+    if result.stderr.is_empty() {
+        result.stdout.clone()
+    } else {
+        format!("{}\n{}", result.stdout, result.stderr)
+    }
+}
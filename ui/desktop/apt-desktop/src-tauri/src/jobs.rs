@@ -0,0 +1,244 @@
+// Job queue across all registered agents.
+//
+// Every async agent queues its own jobs by priority. `list_job_queue` asks
+// each registered agent for `/jobs` and merges the answers into one list,
+// so a shared fleet of load generators can be watched and cleaned up from
+// one place. An agent that cannot be reached only costs its own jobs: its
+// error is reported next to the other agents' queues.
+
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+use tokio::task::JoinSet;
+
+use crate::agent_client::{
+    AgentClient, AgentError, AgentHttp, JobResults, JobStatus, JobSummary, QueueStats,
+};
+use crate::agents::{AgentMode, AgentRegistry, RegistryError};
+use crate::model::Priority;
+use crate::profiles::{ProfileError, ProfileStore};
+use crate::variables::Environment;
+use crate::vault::Vault;
+use crate::workspace::Workspace;
+
+/// One slow agent should not hold up the whole list.
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobQueueError {
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+    #[error(transparent)]
+    Agent(#[from] AgentError),
+}
+
+impl Serialize for JobQueueError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JobQueueError::Registry(e) => e.serialize(serializer),
+            JobQueueError::Profile(e) => e.serialize(serializer),
+            JobQueueError::Agent(e) => e.serialize(serializer),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct QueuedJob {
+    pub agent: String,
+    pub job_id: String,
+    pub status: JobStatus,
+    pub created_at: String,
+    /// Percent.
+    pub progress: Option<f64>,
+    pub priority: Priority,
+    /// 1-based position in the agent's queue; only set while the job is
+    /// queued.
+    pub queue_position: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct AgentQueue {
+    pub agent: String,
+    pub stats: Option<QueueStats>,
+    /// Why the agent's jobs are missing from the list.
+    pub error: Option<AgentError>,
+}
+
+#[derive(Serialize)]
+pub struct JobQueue {
+    /// Running jobs first, then queued jobs in the order they will start,
+    /// then finished jobs, newest first.
+    pub jobs: Vec<QueuedJob>,
+    /// Every agent in `serve` mode, in registry order.
+    pub agents: Vec<AgentQueue>,
+}
+
+/// Queries all agents in `serve` mode at once.
+pub async fn list(
+    registry: &AgentRegistry,
+    http: &AgentHttp,
+    vault: &Vault,
+    environment: &Environment,
+) -> JobQueue {
+    let mut requests = JoinSet::new();
+    for (index, agent) in registry
+        .list()
+        .into_iter()
+        .filter(|agent| agent.config.mode == AgentMode::Serve)
+        .enumerate()
+    {
+        let client = agent.client(http, vault, environment);
+        requests.spawn(async move {
+            let jobs = match client {
+                Ok(client) => client.with_timeout(LIST_TIMEOUT).jobs().await,
+                Err(e) => Err(e),
+            };
+            (index, agent.name, jobs)
+        });
+    }
+
+    let mut responses = Vec::new();
+    while let Some(Ok(response)) = requests.join_next().await {
+        responses.push(response);
+    }
+    responses.sort_by_key(|(index, ..)| *index);
+
+    let mut queue = JobQueue {
+        jobs: Vec::new(),
+        agents: Vec::new(),
+    };
+    for (_, agent, response) in responses {
+        match response {
+            Ok(list) => {
+                queue.jobs.extend(with_queue_positions(&agent, list.jobs));
+                queue.agents.push(AgentQueue {
+                    agent,
+                    stats: Some(list.stats),
+                    error: None,
+                });
+            }
+            Err(e) => queue.agents.push(AgentQueue {
+                agent,
+                stats: None,
+                error: Some(e),
+            }),
+        }
+    }
+    queue.jobs.sort_by(compare_jobs);
+    queue
+}
+
+/// `/jobs` has no queue positions; they are worked out the way the agent
+/// does for `/jobs/{id}`: queued jobs ordered by priority, then by
+/// submission, which is the order `/jobs` lists them in.
+fn with_queue_positions(agent: &str, jobs: Vec<JobSummary>) -> Vec<QueuedJob> {
+    let mut jobs: Vec<QueuedJob> = jobs
+        .into_iter()
+        .map(|job| QueuedJob {
+            agent: agent.to_string(),
+            job_id: job.job_id,
+            status: job.status,
+            created_at: job.created_at,
+            progress: job.progress,
+            priority: job.priority,
+            queue_position: None,
+        })
+        .collect();
+
+    let mut queued: Vec<&mut QueuedJob> = jobs
+        .iter_mut()
+        .filter(|job| job.status == JobStatus::Queued)
+        .collect();
+    // Stable, so jobs of equal priority keep their submission order.
+    queued.sort_by_key(|job| job.priority);
+    for (position, job) in queued.into_iter().enumerate() {
+        job.queue_position = Some(position as u32 + 1);
+    }
+    jobs
+}
+
+fn compare_jobs(a: &QueuedJob, b: &QueuedJob) -> Ordering {
+    fn stage(status: JobStatus) -> u8 {
+        match status {
+            JobStatus::Running => 0,
+            JobStatus::Queued | JobStatus::Pending => 1,
+            JobStatus::Complete | JobStatus::Failed | JobStatus::Cancelled => 2,
+        }
+    }
+
+    stage(a.status)
+        .cmp(&stage(b.status))
+        .then_with(|| match stage(a.status) {
+            // Timestamps are ISO 8601 in the agent's local time, so they
+            // compare as strings.
+            2 => b.created_at.cmp(&a.created_at),
+            _ => (a.priority, a.queue_position, &a.created_at).cmp(&(
+                b.priority,
+                b.queue_position,
+                &b.created_at,
+            )),
+        })
+        .then_with(|| (&a.agent, &a.job_id).cmp(&(&b.agent, &b.job_id)))
+}
+
+fn client(
+    workspace: &Workspace,
+    profiles: &ProfileStore,
+    registry: &AgentRegistry,
+    http: &AgentHttp,
+    vault: &Vault,
+    agent: &str,
+) -> Result<AgentClient, JobQueueError> {
+    let environment = Environment::load(workspace, profiles, None, None)?;
+    Ok(registry.get(agent)?.client(http, vault, &environment)?)
+}
+
+#[tauri::command]
+pub async fn list_job_queue(
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    registry: tauri::State<'_, AgentRegistry>,
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+) -> Result<JobQueue, ProfileError> {
+    // Endpoints and tokens are resolved like for a run without a test file.
+    let environment = Environment::load(&workspace, &profiles, None, None)?;
+    Ok(list(&registry, &http, &vault, &environment).await)
+}
+
+/// Removes the job from the agent's queue, or stops it if it is running.
+#[tauri::command]
+pub async fn cancel_job(
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    registry: tauri::State<'_, AgentRegistry>,
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    agent: String,
+    job_id: String,
+) -> Result<(), JobQueueError> {
+    client(&workspace, &profiles, &registry, &http, &vault, &agent)?
+        .delete_job(&job_id)
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_job_results(
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    registry: tauri::State<'_, AgentRegistry>,
+    http: tauri::State<'_, AgentHttp>,
+    vault: tauri::State<'_, Vault>,
+    agent: String,
+    job_id: String,
+) -> Result<JobResults, JobQueueError> {
+    Ok(
+        client(&workspace, &profiles, &registry, &http, &vault, &agent)?
+            .job_results(&job_id)
+            .await?,
+    )
+}
@@ -0,0 +1,149 @@
+// Parsing of the JUnit XML reports pytest writes with `--junitxml`.
+
+use std::path::Path;
+
+use roxmltree::{Document, Node};
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Error,
+    Skipped,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TestCaseResult {
+    pub classname: String,
+    pub name: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub outcome: TestOutcome,
+    pub duration_secs: f64,
+    pub message: Option<String>,
+    pub details: Option<String>,
+    pub system_out: Option<String>,
+    pub system_err: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct TestSummary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    pub skipped: usize,
+    pub duration_secs: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TestReport {
+    pub summary: TestSummary,
+    pub cases: Vec<TestCaseResult>,
+}
+
+pub fn read_report(path: &Path) -> Result<TestReport, String> {
+    let xml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_report(&xml)
+}
+
+/// Parses a JUnit XML document.
+///
+/// pytest writes a `<testsuites>` root since 5.1 and a bare `<testsuite>`
+/// before that; both are accepted.
+pub fn parse_report(xml: &str) -> Result<TestReport, String> {
+    let doc = Document::parse(xml).map_err(|e| format!("invalid JUnit XML: {}", e))?;
+
+    let cases: Vec<TestCaseResult> = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("testcase"))
+        .map(parse_test_case)
+        .collect();
+
+    let mut summary = TestSummary {
+        total: cases.len(),
+        ..Default::default()
+    };
+    for case in &cases {
+        match case.outcome {
+            TestOutcome::Passed => summary.passed += 1,
+            TestOutcome::Failed => summary.failed += 1,
+            TestOutcome::Error => summary.errors += 1,
+            TestOutcome::Skipped => summary.skipped += 1,
+        }
+    }
+
+    // Prefer the suite-level wall time, which includes fixture setup and
+    // teardown that the per-case times leave out.
+    summary.duration_secs = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("testsuite"))
+        .filter_map(|node| parse_f64(node.attribute("time")))
+        .reduce(|a, b| a + b)
+        .unwrap_or_else(|| cases.iter().map(|case| case.duration_secs).sum());
+
+    Ok(TestReport { summary, cases })
+}
+
+fn parse_test_case(node: Node) -> TestCaseResult {
+    let mut outcome = TestOutcome::Passed;
+    let mut message = None;
+    let mut details = None;
+    let mut system_out = None;
+    let mut system_err = None;
+
+    for child in node.children().filter(|child| child.is_element()) {
+        let child_outcome = match child.tag_name().name() {
+            "failure" => Some(TestOutcome::Failed),
+            "error" => Some(TestOutcome::Error),
+            "skipped" => Some(TestOutcome::Skipped),
+            "system-out" => {
+                system_out = element_text(child);
+                None
+            }
+            "system-err" => {
+                system_err = element_text(child);
+                None
+            }
+            _ => None,
+        };
+
+        // A test that fails and then errors in teardown is reported with
+        // both elements; keep the first one as the primary outcome.
+        if let Some(child_outcome) = child_outcome {
+            if outcome == TestOutcome::Passed {
+                outcome = child_outcome;
+                message = child.attribute("message").map(str::to_string);
+                details = element_text(child);
+            }
+        }
+    }
+
+    TestCaseResult {
+        classname: node.attribute("classname").unwrap_or_default().to_string(),
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        file: node.attribute("file").map(str::to_string),
+        line: node.attribute("line").and_then(|line| line.parse().ok()),
+        outcome,
+        duration_secs: parse_f64(node.attribute("time")).unwrap_or_default(),
+        message,
+        details,
+        system_out,
+        system_err,
+    }
+}
+
+fn element_text(node: Node) -> Option<String> {
+    let text: String = node.children().filter_map(|child| child.text()).collect();
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn parse_f64(value: Option<&str>) -> Option<f64> {
+    value.and_then(|value| value.trim().parse().ok())
+}
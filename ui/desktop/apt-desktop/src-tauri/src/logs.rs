@@ -0,0 +1,315 @@
+// Live agent logs over SSH.
+//
+// Reads the same logs as `aptcli agent logs --tail` (`AgentDeployer.get_logs`)
+// but keeps following them: `docker logs -f` for Docker agents,
+// `journalctl -fu` for systemd units and `tail -F` of `agent.log` in the
+// install directory for cron and shell agents. Every open stream has a
+// subscription id; its lines are emitted as `agent-log` events on the window
+// that opened it until the stream is closed or the command exits.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Serialize, Serializer};
+use tokio::sync::oneshot;
+
+use crate::agents::{AgentRegistry, DeploymentType, RegistryError};
+use crate::deploy::DEFAULT_REMOTE_DIR;
+use crate::ssh::{self, SshClient, SshError};
+
+pub const AGENT_LOG_EVENT: &str = "agent-log";
+pub const AGENT_LOG_CLOSED_EVENT: &str = "agent-log-closed";
+
+/// Lines of history shown before following, as `aptcli agent logs`.
+const DEFAULT_TAIL: u32 = 100;
+/// Longer lines are emitted in pieces.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogError {
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Ssh(#[from] SshError),
+    #[error("agent '{0}' has no SSH target")]
+    NoSshTarget(String),
+    #[error("agent '{0}' has no deployment type")]
+    NoDeploymentType(String),
+    #[error("log subscription '{0}' does not exist")]
+    UnknownSubscription(String),
+}
+
+impl LogError {
+    fn kind(&self) -> &'static str {
+        match self {
+            LogError::Registry(_) => "registry",
+            LogError::Ssh(_) => "ssh",
+            LogError::NoSshTarget(_) => "no_ssh_target",
+            LogError::NoDeploymentType(_) => "no_deployment_type",
+            LogError::UnknownSubscription(_) => "subscription_not_found",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// agent name or the subscription id.
+impl Serialize for LogError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            LogError::Registry(e) => return e.serialize(serializer),
+            LogError::Ssh(e) => return e.serialize(serializer),
+            LogError::NoSshTarget(path)
+            | LogError::NoDeploymentType(path)
+            | LogError::UnknownSubscription(path) => Some(path.as_str()),
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct AgentLogLine {
+    pub subscription_id: String,
+    pub agent: String,
+    pub line: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AgentLogClosed {
+    pub subscription_id: String,
+    pub agent: String,
+    /// Exit status of the log command; `None` when the stream was closed.
+    pub exit_status: Option<u32>,
+    /// Closed with `close_agent_logs` rather than by the command exiting.
+    pub closed: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LogSubscription {
+    pub subscription_id: String,
+    pub agent: String,
+    /// The command run on the agent host.
+    pub command: String,
+}
+
+struct Subscription {
+    info: LogSubscription,
+    stop: oneshot::Sender<()>,
+}
+
+/// Open log streams, keyed by subscription id.
+#[derive(Default)]
+pub struct LogStreams {
+    streams: Arc<Mutex<HashMap<String, Subscription>>>,
+}
+
+impl LogStreams {
+    pub fn list(&self) -> Vec<LogSubscription> {
+        let mut subscriptions: Vec<LogSubscription> = self
+            .streams
+            .lock()
+            .unwrap()
+            .values()
+            .map(|subscription| subscription.info.clone())
+            .collect();
+        subscriptions.sort_by(|a, b| a.agent.cmp(&b.agent));
+        subscriptions
+    }
+
+    /// Stops the stream; its `agent-log-closed` event follows once the
+    /// connection is closed.
+    pub fn close(&self, subscription_id: &str) -> Result<(), LogError> {
+        let subscription = self
+            .streams
+            .lock()
+            .unwrap()
+            .remove(subscription_id)
+            .ok_or_else(|| LogError::UnknownSubscription(subscription_id.to_string()))?;
+        let _ = subscription.stop.send(());
+        Ok(())
+    }
+}
+
+/// The follow-mode counterpart of the commands `AgentDeployer.get_logs`
+/// runs.
+pub fn log_command(
+    deployment: DeploymentType,
+    agent: &str,
+    remote_dir: &str,
+    tail: u32,
+    sudo: bool,
+) -> String {
+    let sudo = if sudo { "sudo -n " } else { "" };
+    match deployment {
+        DeploymentType::Docker => {
+            format!("{sudo}docker logs -f --tail {tail} {}", ssh::quote(agent))
+        }
+        DeploymentType::Systemd => format!(
+            "{sudo}journalctl -fu {} -n {tail} --no-pager",
+            ssh::quote(agent)
+        ),
+        DeploymentType::Cron | DeploymentType::Shell => format!(
+            "tail -n {tail} -F {}",
+            ssh::quote(&format!("{}/agent.log", remote_dir.trim_end_matches('/')))
+        ),
+    }
+}
+
+/// Splits output into lines. Commands run on a pseudo-terminal, so lines
+/// end in `\r\n`.
+#[derive(Default)]
+struct Lines {
+    partial: Vec<u8>,
+}
+
+impl Lines {
+    fn push(&mut self, data: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(data);
+        let mut lines = Vec::new();
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            lines.push(text(&line[..end]));
+        }
+        if self.partial.len() > MAX_LINE_LEN {
+            lines.push(text(&std::mem::take(&mut self.partial)));
+        }
+        lines
+    }
+
+    fn finish(self) -> Option<String> {
+        (!self.partial.is_empty()).then(|| text(&self.partial))
+    }
+}
+
+fn text(line: &[u8]) -> String {
+    String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)).into_owned()
+}
+
+/// Connects to the agent's host and starts following its log. Fails if
+/// the host cannot be reached; a log command that fails on the host shows
+/// up as its output followed by `agent-log-closed`.
+#[tauri::command]
+pub async fn open_agent_logs(
+    window: tauri::Window,
+    streams: tauri::State<'_, LogStreams>,
+    ssh: tauri::State<'_, SshClient>,
+    registry: tauri::State<'_, AgentRegistry>,
+    name: String,
+    tail: Option<u32>,
+    remote_dir: Option<String>,
+) -> Result<LogSubscription, LogError> {
+    let agent = registry.get(&name)?;
+    let target = agent
+        .config
+        .ssh
+        .clone()
+        .ok_or_else(|| LogError::NoSshTarget(name.clone()))?;
+    let deployment = agent
+        .config
+        .deployment
+        .ok_or_else(|| LogError::NoDeploymentType(name.clone()))?;
+    let command = log_command(
+        deployment,
+        &name,
+        remote_dir.as_deref().unwrap_or(DEFAULT_REMOTE_DIR),
+        tail.unwrap_or(DEFAULT_TAIL),
+        target.user != "root",
+    );
+
+    let mut session = ssh.connect(&target).await?;
+    let mut output = match session.stream(&command).await {
+        Ok(output) => output,
+        Err(e) => {
+            session.close().await;
+            return Err(e.into());
+        }
+    };
+
+    let info = LogSubscription {
+        subscription_id: uuid::Uuid::new_v4().to_string(),
+        agent: name,
+        command,
+    };
+    let (stop, mut stopped) = oneshot::channel();
+    streams.streams.lock().unwrap().insert(
+        info.subscription_id.clone(),
+        Subscription {
+            info: info.clone(),
+            stop,
+        },
+    );
+
+    let open_streams = streams.streams.clone();
+    let subscription_id = info.subscription_id.clone();
+    let agent = info.agent.clone();
+    tauri::async_runtime::spawn(async move {
+        let emit = |line: String| {
+            let _ = window.emit(
+                AGENT_LOG_EVENT,
+                AgentLogLine {
+                    subscription_id: subscription_id.clone(),
+                    agent: agent.clone(),
+                    line,
+                },
+            );
+        };
+
+        let mut lines = Lines::default();
+        let closed = loop {
+            tokio::select! {
+                _ = &mut stopped => break true,
+                data = output.next() => match data {
+                    Some(data) => {
+                        for line in lines.push(&data) {
+                            emit(line);
+                        }
+                    }
+                    None => break false,
+                },
+            }
+        };
+        if let Some(line) = lines.finish() {
+            emit(line);
+        }
+        let exit_status = output.exit_status();
+        output.close().await;
+        session.close().await;
+
+        open_streams.lock().unwrap().remove(&subscription_id);
+        let _ = window.emit(
+            AGENT_LOG_CLOSED_EVENT,
+            AgentLogClosed {
+                subscription_id,
+                agent,
+                exit_status,
+                closed,
+            },
+        );
+    });
+
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn close_agent_logs(
+    streams: tauri::State<'_, LogStreams>,
+    subscription_id: String,
+) -> Result<(), LogError> {
+    streams.close(&subscription_id)
+}
+
+#[tauri::command]
+pub fn list_agent_logs(streams: tauri::State<'_, LogStreams>) -> Vec<LogSubscription> {
+    streams.list()
+}
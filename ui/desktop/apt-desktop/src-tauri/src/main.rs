@@ -1,46 +1,238 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::process::Command;
+mod agent_client;
+mod agents;
+mod deploy;
+mod discovery;
+mod health;
+mod history;
+mod jobs;
+mod junit;
+mod logs;
+mod model;
+mod packages;
+mod profiles;
+mod runner;
+mod ssh;
+mod validation;
+mod variables;
+mod vault;
+mod versions;
+mod watcher;
+mod workspace;
+mod yaml_edit;
+mod yaml_tree;
+
+use std::path::{Path, PathBuf};
+
+use agent_client::AgentHttp;
+use agents::AgentRegistry;
+use deploy::Deployer;
+use health::HealthMonitor;
+use history::{RunHistory, RunKind};
+use logs::LogStreams;
+use profiles::ProfileStore;
+use runner::{RunRegistry, RunResult};
+use ssh::SshClient;
 use tauri::Manager;
+use tokio::process::Command;
+use variables::Environment;
+use vault::Vault;
+use versions::FileVersions;
+use watcher::WorkspaceWatcher;
+use workspace::{Workspace, WorkspaceError};
 
-#[tauri::command]
-fn run_pytest(test_file: String) -> Result<String, String> {
-    let output = Command::new("pytest")
-        .arg(&test_file)
-        .arg("-v")
-        .output()
+const PYTEST_ARGS: &[&str] = &["-v"];
+
+fn pytest_command(test_file: &str, junit_xml: &Path, environment: &RunEnvironment) -> Command {
+    let mut command = Command::new("pytest");
+    command
+        .arg(test_file)
+        .args(PYTEST_ARGS)
+        .arg(format!("--junitxml={}", junit_xml.display()))
+        .envs(environment.variables.iter().cloned())
+        // Python block-buffers piped stdout; force line-by-line output.
+        .env("PYTHONUNBUFFERED", "1");
+    command
+}
+
+struct RunEnvironment {
+    profile: Option<String>,
+    variables: Vec<(String, String)>,
+}
+
+/// Variables of the selected profile, plus the workspace `.env` of the test
+/// file if there is one. Secrets are read from the vault here, right before
+/// the child process starts.
+fn run_environment(
+    workspace: &Workspace,
+    profiles: &ProfileStore,
+    vault: &Vault,
+    test_file: Option<&str>,
+) -> Result<RunEnvironment, String> {
+    let environment = Environment::load(workspace, profiles, test_file.map(Path::new), None)
+        .map_err(|e| e.to_string())?;
+    let definition = test_file
+        .map(std::fs::read_to_string)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let variables = environment
+        .child_variables(vault, definition.as_deref())
         .map_err(|e| e.to_string())?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(RunEnvironment {
+        profile: environment.profile,
+        variables,
+    })
+}
+
+fn junit_xml_path() -> PathBuf {
+    std::env::temp_dir().join(format!("apt-junit-{}.xml", uuid::Uuid::new_v4()))
+}
+
+fn record_run(
+    history: &RunHistory,
+    kind: RunKind,
+    test_file: Option<&str>,
+    profile: Option<&str>,
+    args: &[String],
+    result: &RunResult,
+) {
+    // A failure to persist history must not turn a finished run into an error.
+    if let Err(e) = history.record(kind, test_file, profile, args, result) {
+        eprintln!("failed to record run {}: {}", result.run_id, e);
+    }
+}
+
+fn resolve_test_file(workspace: &Workspace, test_file: &str) -> Result<String, String> {
+    workspace
+        .resolve_existing(test_file)
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
 
-    Ok(format!("{}\n{}", stdout, stderr))
+fn pytest_args() -> Vec<String> {
+    PYTEST_ARGS.iter().map(|arg| arg.to_string()).collect()
 }
 
 #[tauri::command]
-fn run_aptcli(args: Vec<String>) -> Result<String, String> {
-    let output = Command::new("aptcli")
-        .args(&args)
-        .output()
-        .map_err(|e| e.to_string())?;
+async fn run_pytest(
+    registry: tauri::State<'_, RunRegistry>,
+    history: tauri::State<'_, RunHistory>,
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    vault: tauri::State<'_, Vault>,
+    test_file: String,
+    run_id: Option<String>,
+) -> Result<RunResult, String> {
+    let test_file = resolve_test_file(&workspace, &test_file)?;
+    let environment = run_environment(&workspace, &profiles, &vault, Some(&test_file))?;
+    let run_id = run_id.unwrap_or_else(runner::new_run_id);
+    let junit_xml = junit_xml_path();
+    registry.spawn(
+        run_id.clone(),
+        pytest_command(&test_file, &junit_xml, &environment),
+        None,
+        Some(junit_xml),
+    )?;
+    let result = registry.wait(&run_id, None).await?;
+    record_run(
+        &history,
+        RunKind::Pytest,
+        Some(&test_file),
+        environment.profile.as_deref(),
+        &pytest_args(),
+        &result,
+    );
+
+    Ok(result)
+}
+
+#[tauri::command]
+async fn run_pytest_streaming(
+    window: tauri::Window,
+    registry: tauri::State<'_, RunRegistry>,
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    vault: tauri::State<'_, Vault>,
+    test_file: String,
+    run_id: Option<String>,
+) -> Result<String, String> {
+    let test_file = resolve_test_file(&workspace, &test_file)?;
+    let environment = run_environment(&workspace, &profiles, &vault, Some(&test_file))?;
+    // The frontend may pick the id itself so it can subscribe before the
+    // first line is emitted.
+    let run_id = run_id.unwrap_or_else(runner::new_run_id);
+    let junit_xml = junit_xml_path();
+    let app = window.app_handle();
+    registry.spawn(
+        run_id.clone(),
+        pytest_command(&test_file, &junit_xml, &environment),
+        Some(window),
+        Some(junit_xml),
+    )?;
+
+    let waited_run_id = run_id.clone();
+    let profile = environment.profile;
+    tauri::async_runtime::spawn(async move {
+        let registry = app.state::<RunRegistry>();
+        if let Ok(result) = registry.wait(&waited_run_id, None).await {
+            record_run(
+                &app.state::<RunHistory>(),
+                RunKind::Pytest,
+                Some(&test_file),
+                profile.as_deref(),
+                &pytest_args(),
+                &result,
+            );
+        }
+    });
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(run_id)
+}
+
+#[tauri::command]
+async fn run_aptcli(
+    registry: tauri::State<'_, RunRegistry>,
+    history: tauri::State<'_, RunHistory>,
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    vault: tauri::State<'_, Vault>,
+    args: Vec<String>,
+    run_id: Option<String>,
+) -> Result<RunResult, String> {
+    let environment = run_environment(&workspace, &profiles, &vault, None)?;
+    let run_id = run_id.unwrap_or_else(runner::new_run_id);
+    let mut command = Command::new("aptcli");
+    command.args(&args).envs(environment.variables);
+    registry.spawn(run_id.clone(), command, None, None)?;
+    let result = registry.wait(&run_id, None).await?;
+    record_run(
+        &history,
+        RunKind::Aptcli,
+        None,
+        environment.profile.as_deref(),
+        &args,
+        &result,
+    );
 
-    Ok(format!("{}\n{}", stdout, stderr))
+    Ok(result)
 }
 
 #[tauri::command]
-fn get_test_files(directory: String) -> Result<Vec<String>, String> {
+fn get_test_files(
+    workspace: tauri::State<'_, Workspace>,
+    directory: String,
+) -> Result<Vec<String>, WorkspaceError> {
     use std::fs;
-    
-    let paths = fs::read_dir(&directory)
-        .map_err(|e| e.to_string())?;
+
+    let directory = workspace.resolve_existing(&directory)?;
+    let paths = fs::read_dir(&directory).map_err(|e| WorkspaceError::io(&directory, e))?;
 
     let mut files = Vec::new();
     for path in paths {
-        let path = path.map_err(|e| e.to_string())?;
+        let path = path.map_err(|e| WorkspaceError::io(&directory, e))?;
         let file_name = path.file_name().to_string_lossy().to_string();
         if file_name.ends_with(".yml") || file_name.ends_with(".yaml") {
             files.push(path.path().to_string_lossy().to_string());
@@ -51,22 +243,150 @@ fn get_test_files(directory: String) -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-fn read_yaml_file(file_path: String) -> Result<String, String> {
+fn read_yaml_file(
+    workspace: tauri::State<'_, Workspace>,
+    file_path: String,
+) -> Result<String, WorkspaceError> {
     use std::fs;
-    fs::read_to_string(&file_path).map_err(|e| e.to_string())
+
+    let file_path = workspace.resolve_existing(&file_path)?;
+    fs::read_to_string(&file_path).map_err(|e| WorkspaceError::io(&file_path, e))
 }
 
 #[tauri::command]
-fn write_yaml_file(file_path: String, content: String) -> Result<(), String> {
-    use std::fs;
-    fs::write(&file_path, content).map_err(|e| e.to_string())
+fn write_yaml_file(
+    workspace: tauri::State<'_, Workspace>,
+    versions: tauri::State<'_, FileVersions>,
+    file_path: String,
+    content: String,
+) -> Result<(), WorkspaceError> {
+    let file_path = workspace.resolve_for_write(&file_path)?;
+    versions.save(&file_path, content.as_bytes())
+}
+
+/// Writes the test definition JSON Schema to `output`, or to stdout.
+fn write_schema(output: Option<&str>) -> Result<(), String> {
+    let schema = serde_json::to_string_pretty(&model::schema()).map_err(|e| e.to_string())?;
+    match output {
+        Some(path) => std::fs::write(path, schema + "\n").map_err(|e| e.to_string()),
+        None => {
+            println!("{}", schema);
+            Ok(())
+        }
+    }
 }
 
 fn main() {
+    // `apt-desktop schema [FILE]` exports the schema for external editors
+    // without starting the UI.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("schema") {
+        if let Err(e) = write_schema(args.get(1).map(String::as_str)) {
+            eprintln!("failed to write schema: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     tauri::Builder::default()
+        .manage(RunRegistry::default())
+        .manage(Deployer::default())
+        .manage(LogStreams::default())
+        .setup(|app| {
+            let data_dir = app
+                .path_resolver()
+                .app_data_dir()
+                .ok_or("failed to resolve the app data directory")?;
+            app.manage(RunHistory::open(&data_dir)?);
+            app.manage(FileVersions::new(&data_dir));
+            app.manage(ProfileStore::load(&data_dir));
+            app.manage(Vault::new(&data_dir));
+            app.manage(AgentRegistry::load(&data_dir));
+            app.manage(AgentHttp::new()?);
+            app.manage(HealthMonitor::load(&data_dir));
+            app.manage(SshClient::new(&data_dir));
+
+            let workspace = Workspace::load(&data_dir);
+            let watcher = WorkspaceWatcher::new(app.handle());
+            if let Err(e) = watcher.watch(&workspace.root_paths()) {
+                eprintln!("failed to watch workspace roots: {}", e);
+            }
+            app.manage(workspace);
+            app.manage(watcher);
+            health::spawn(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             run_pytest,
+            run_pytest_streaming,
             run_aptcli,
+            runner::list_runs,
+            runner::cancel_run,
+            runner::wait_run,
+            history::query_run_history,
+            history::get_run_history_entry,
+            history::read_run_output,
+            history::delete_run_history,
+            history::clear_run_history,
+            history::get_run_statistics,
+            workspace::list_workspace_roots,
+            workspace::open_workspace_root,
+            workspace::remove_workspace_root,
+            discovery::discover_test_definitions,
+            model::parse_test_definition,
+            model::serialize_test_definition,
+            model::get_test_definition_schema,
+            validation::validate_test_definition,
+            variables::preview_variables,
+            agents::list_agents,
+            agents::get_agent,
+            agents::create_agent,
+            agents::update_agent,
+            agents::delete_agent,
+            agents::import_agents,
+            health::get_agent_health_states,
+            health::check_agent_health_now,
+            health::get_health_monitor_settings,
+            health::set_health_monitor_settings,
+            agent_client::get_agent_health,
+            agent_client::execute_on_agent,
+            agent_client::submit_agent_job,
+            agent_client::get_agent_job,
+            agent_client::get_agent_job_results,
+            agent_client::delete_agent_job,
+            agent_client::list_agent_jobs,
+            agent_client::get_agent_stats,
+            deploy::deploy_agent,
+            logs::open_agent_logs,
+            logs::close_agent_logs,
+            logs::list_agent_logs,
+            packages::preview_agent_package,
+            packages::write_agent_package,
+            packages::export_agent_package,
+            jobs::list_job_queue,
+            jobs::cancel_job,
+            jobs::get_job_results,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::rename_profile,
+            profiles::delete_profile,
+            profiles::select_profile,
+            profiles::set_profile_variable,
+            profiles::remove_profile_variable,
+            vault::get_vault_status,
+            vault::create_vault,
+            vault::unlock_vault,
+            vault::lock_vault,
+            vault::list_secrets,
+            vault::get_secret,
+            vault::store_secret,
+            vault::rotate_secret,
+            vault::delete_secret,
+            yaml_edit::apply_yaml_edits,
+            yaml_edit::edit_yaml_file,
+            versions::list_file_versions,
+            versions::diff_file_version,
+            versions::restore_file_version,
             get_test_files,
             read_yaml_file,
             write_yaml_file
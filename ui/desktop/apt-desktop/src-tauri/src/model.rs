@@ -0,0 +1,326 @@
+// Typed model of the unified YAML test definition format.
+//
+// Mirrors what `src/core/unified_yaml_loader.py` reads. Every section keeps
+// keys it does not know about in `extra`, so a parse/serialize round trip
+// does not drop settings that newer framework versions understand. Free-form
+// values (request bodies, step context, k6 options) stay as JSON values.
+
+use indexmap::IndexMap;
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+
+pub type Extra = IndexMap<String, Value>;
+
+// Patterns published in the JSON Schema; `validation` checks the same fields
+// and reports line/column diagnostics.
+const DURATION_PATTERN: &str = r"^(\d+(\.\d+)?|(\d+(\.\d+)?(ns|us|µs|ms|s|m|h))+)$";
+const THRESHOLD_PATTERN: &str = r"^\s*(avg|min|max|med|count|rate|value|p\(\s*\d+(\.\d+)?\s*\))\s*(===|==|!=|<=|>=|<|>)\s*-?\d+(\.\d+)?\s*$";
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TestDefinition {
+    pub test_info: Option<TestInfo>,
+    pub k6_tests: Option<IndexMap<String, K6Test>>,
+    pub jmeter_tests: Option<IndexMap<String, JmeterTest>>,
+    pub workflows: Option<IndexMap<String, Workflow>>,
+    pub agents: Option<IndexMap<String, AgentDefinition>>,
+    /// Playwright scenarios, passed through to the browser test runner as is.
+    pub ui_tests: Option<Value>,
+    pub monitoring: Option<Monitoring>,
+    pub reporting: Option<Reporting>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TestInfo {
+    pub test_suite_name: Option<String>,
+    pub test_suite_type: Option<String>,
+    pub description: Option<String>,
+    // Unquoted versions such as `version: 2.0` parse as numbers.
+    #[serde(default, deserialize_with = "string_or_number")]
+    #[schemars(extend("type" = ["string", "number", "null"]))]
+    pub version: Option<String>,
+    pub tags: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Scenario {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub method: Option<String>,
+    pub body: Option<Value>,
+    pub headers: Option<IndexMap<String, String>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+/// An entry of `k6_tests`, also used for the `k6_config` of workflow steps.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct K6Test {
+    pub tool: Option<String>,
+    #[serde(default)]
+    pub scenarios: Vec<Scenario>,
+    pub options: Option<K6Options>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct K6Options {
+    pub vus: Option<u32>,
+    /// k6 duration string such as `30s`, `2m` or `1h30m`.
+    #[schemars(regex(pattern = DURATION_PATTERN))]
+    pub duration: Option<String>,
+    pub iterations: Option<u32>,
+    /// Metric name to threshold expressions, e.g. `http_req_duration:
+    /// ["p(95)<500"]`.
+    pub thresholds: Option<IndexMap<String, Vec<Threshold>>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Threshold {
+    Expression(#[schemars(regex(pattern = THRESHOLD_PATTERN))] String),
+    Detailed {
+        #[schemars(regex(pattern = THRESHOLD_PATTERN))]
+        threshold: String,
+        #[serde(rename = "abortOnFail", skip_serializing_if = "Option::is_none")]
+        abort_on_fail: Option<bool>,
+        #[serde(rename = "delayAbortEval", skip_serializing_if = "Option::is_none")]
+        delay_abort_eval: Option<String>,
+    },
+}
+
+/// An entry of `jmeter_tests`, also used for the `jmeter_config` of workflow
+/// steps.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct JmeterTest {
+    pub tool: Option<String>,
+    #[serde(default)]
+    pub scenarios: Vec<Scenario>,
+    pub thread_group_config: Option<ThreadGroupConfig>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ThreadGroupConfig {
+    pub num_threads: Option<u32>,
+    /// Seconds.
+    pub ramp_time: Option<u32>,
+    /// Seconds.
+    pub duration: Option<u32>,
+    pub loops: Option<i64>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Workflow {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub iterations: Option<u32>,
+    pub concurrency: Option<u32>,
+    pub concurrent_users: Option<u32>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+    pub aggregator: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StepAction {
+    ApiCall,
+    K6Test,
+    JmeterTest,
+    AgentQuery,
+    #[default]
+    Custom,
+}
+
+/// Job priority on async agents, highest first.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Urgent,
+    High,
+    Normal,
+    Low,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct IterationConfig {
+    /// Seconds to sleep between iterations of the step.
+    pub delay_between: Option<f64>,
+    pub fail_on_error: Option<bool>,
+    pub track_individual: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Step {
+    pub name: String,
+    /// Omitted in most agent steps; the loader treats that as `custom`.
+    pub action: Option<StepAction>,
+    /// Agent that runs the step; the step runs locally when unset.
+    pub agent: Option<String>,
+    pub priority: Option<Priority>,
+    pub iterations: Option<u32>,
+    pub iteration_config: Option<IterationConfig>,
+
+    // `custom` steps run code on an agent.
+    pub code: Option<String>,
+    pub code_file: Option<String>,
+    pub language: Option<String>,
+    pub context: Option<IndexMap<String, Value>>,
+    pub tags: Option<IndexMap<String, Value>>,
+    /// Seconds.
+    pub timeout: Option<f64>,
+
+    // `api_call` steps.
+    pub url: Option<String>,
+    pub method: Option<String>,
+    pub body: Option<Value>,
+    pub headers: Option<IndexMap<String, String>>,
+
+    pub k6_config: Option<K6Test>,
+    pub jmeter_config: Option<JmeterTest>,
+
+    // `agent_query` steps.
+    pub metric: Option<String>,
+    pub timerange: Option<String>,
+    pub filters: Option<IndexMap<String, Value>>,
+    pub limit: Option<u32>,
+
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AgentDefinition {
+    /// Usually an environment reference such as `${LOAD_AGENT_ENDPOINT}`.
+    pub endpoint: Option<String>,
+    pub auth_token: Option<String>,
+    /// Seconds.
+    pub timeout: Option<u64>,
+    /// Seconds.
+    pub health_check_interval: Option<u64>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Monitoring {
+    pub influxdb: Option<InfluxDbConfig>,
+    pub grafana: Option<GrafanaConfig>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct InfluxDbConfig {
+    pub enabled: Option<bool>,
+    pub url: Option<String>,
+    pub database: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GrafanaConfig {
+    pub enabled: Option<bool>,
+    pub dashboard_url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Reporting {
+    pub output_dir: Option<String>,
+    /// Tools whose results go into the report (`k6`, `jmeter`, `workflows`,
+    /// ...).
+    pub include: Option<Vec<String>>,
+    pub formats: Option<Vec<String>>,
+    pub unified_report: Option<bool>,
+    pub report_name: Option<String>,
+    pub options: Option<IndexMap<String, Value>>,
+    #[serde(flatten)]
+    pub extra: Extra,
+}
+
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(s)) => Some(s),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        Some(Value::Null) | None => None,
+        Some(other) => {
+            return Err(serde::de::Error::custom(format!(
+                "expected a string or number, found {}",
+                other
+            )))
+        }
+    })
+}
+
+pub fn parse(content: &str) -> Result<TestDefinition, serde_yaml::Error> {
+    serde_yaml::from_str(content)
+}
+
+pub fn serialize(definition: &TestDefinition) -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(definition)
+}
+
+/// JSON Schema for test definition files. Draft-07 is used because it is the
+/// newest draft VS Code's YAML extension fully supports.
+pub fn schema() -> Value {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<TestDefinition>()
+        .to_value()
+}
+
+#[tauri::command]
+pub fn parse_test_definition(content: String) -> Result<TestDefinition, String> {
+    parse(&content).map_err(|e| e.to_string())
+}
+
+/// Serializes a definition back to YAML. Comments and formatting of the
+/// original file are not preserved.
+#[tauri::command]
+pub fn serialize_test_definition(definition: TestDefinition) -> Result<String, String> {
+    serialize(&definition).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_test_definition_schema() -> Value {
+    schema()
+}
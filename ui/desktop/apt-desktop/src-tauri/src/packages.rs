@@ -0,0 +1,694 @@
+// Agent packages, generated natively.
+//
+// Produces the packages of `src/agents/provisioner.py`'s `AgentProvisioner`
+// (`aptcli agent create`) from a typed spec: a Dockerfile and compose file,
+// a cron script and crontab entry, a systemd unit and install script, or a
+// shell launcher, each with the agent server and its `config.json`. Files
+// can be previewed before they are written to a package directory (where
+// `deploy_agent` picks them up) or exported as a tar.gz bundle.
+//
+// Unlike the Python provisioner, the generated files refer to the install
+// directory on the agent host rather than to the local package directory,
+// so a bundle works wherever it is unpacked to that directory.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::agents::{self, AgentMode, DeploymentType, RegistryError};
+use crate::deploy::DEFAULT_REMOTE_DIR;
+use crate::profiles::{ProfileError, ProfileStore};
+use crate::variables::Environment;
+use crate::vault::{Vault, VaultError};
+use crate::versions;
+use crate::workspace::{Workspace, WorkspaceError};
+
+/// The async agent server, shipped as `agent_server.py` like the
+/// provisioner does.
+const AGENT_SERVER: &str = include_str!("../../../../../src/agents/agent_server_async.py");
+const DEFAULT_PORT: u16 = 9090;
+const DEFAULT_SCHEDULE: &str = "*/5 * * * *";
+/// As in `src/agents/config.example.json`.
+const DEFAULT_ALLOWED_MODULES: &[&str] = &[
+    "requests",
+    "json",
+    "time",
+    "datetime",
+    "random",
+    "playwright.sync_api",
+    "subprocess",
+];
+
+const DOCKER_REQUIREMENTS: &str = "fastapi==0.104.1
+uvicorn==0.24.0
+aiohttp==3.9.0
+pydantic==2.5.0
+influxdb-client==1.38.0
+requests==2.31.0
+";
+const REQUIREMENTS: &str = "fastapi==0.104.1
+uvicorn==0.24.0
+aiohttp==3.9.0
+pydantic==2.5.0
+";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PackageError {
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Profile(#[from] ProfileError),
+    #[error(transparent)]
+    Vault(#[from] VaultError),
+    #[error("environment variable '{0}' is not set")]
+    UnsetVariable(String),
+    #[error("invalid {0}: {1}")]
+    InvalidSpec(String, String),
+    #[error("cannot build the bundle of {0}: {1}")]
+    Bundle(String, String),
+}
+
+impl PackageError {
+    fn kind(&self) -> &'static str {
+        match self {
+            PackageError::Workspace(_) => "workspace",
+            PackageError::Registry(_) => "registry",
+            PackageError::Profile(_) => "profile",
+            PackageError::Vault(_) => "vault",
+            PackageError::UnsetVariable(_) => "variable_not_set",
+            PackageError::InvalidSpec(..) => "invalid_spec",
+            PackageError::Bundle(..) => "bundle",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// variable name, the invalid field or the agent name.
+impl Serialize for PackageError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            PackageError::Workspace(e) => return e.serialize(serializer),
+            PackageError::Registry(e) => return e.serialize(serializer),
+            PackageError::Profile(e) => return e.serialize(serializer),
+            PackageError::Vault(e) => return e.serialize(serializer),
+            PackageError::UnsetVariable(path)
+            | PackageError::InvalidSpec(path, _)
+            | PackageError::Bundle(path, _) => Some(path.as_str()),
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// What `aptcli agent create` is told, plus the server settings of
+/// `config.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackageSpec {
+    /// Also the `agent_id`, the container name and the systemd unit name.
+    pub name: String,
+    pub deployment: DeploymentType,
+    #[serde(default)]
+    pub mode: AgentMode,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Usually a `${secret:<name>}` or `${VAR}` reference; it is resolved
+    /// when the package is written, and shown as is in previews.
+    pub auth_token: Option<String>,
+    #[serde(default = "default_max_concurrent_jobs")]
+    pub max_concurrent_jobs: u32,
+    #[serde(default = "default_max_queued_jobs")]
+    pub max_queued_jobs: u32,
+    /// Seconds.
+    #[serde(default = "default_job_timeout")]
+    pub job_timeout: u64,
+    #[serde(default = "default_allowed_modules")]
+    pub allowed_modules: Vec<String>,
+    /// InfluxDB URL of `emit` agents; only used by Docker packages.
+    pub emit_target: Option<String>,
+    /// Crontab schedule of cron packages, `*/5 * * * *` by default.
+    pub schedule: Option<String>,
+    /// Where the package is installed on the agent host, `/opt/apt-agent`
+    /// by default; the systemd unit and the crontab entry refer to it.
+    pub install_dir: Option<String>,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_max_concurrent_jobs() -> u32 {
+    3
+}
+
+fn default_max_queued_jobs() -> u32 {
+    10
+}
+
+fn default_job_timeout() -> u64 {
+    3600
+}
+
+fn default_allowed_modules() -> Vec<String> {
+    DEFAULT_ALLOWED_MODULES
+        .iter()
+        .map(|module| module.to_string())
+        .collect()
+}
+
+/// `config.json`, as `AgentConfig` in `agent_server_async.py` reads it.
+#[derive(Serialize)]
+struct ServerConfig<'a> {
+    agent_id: &'a str,
+    mode: AgentMode,
+    port: u16,
+    auth_token: Option<&'a str>,
+    max_concurrent_jobs: u32,
+    max_queued_jobs: u32,
+    job_timeout: u64,
+    allowed_modules: &'a [String],
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GeneratedFile {
+    /// Relative to the package directory.
+    pub path: String,
+    pub content: String,
+    pub executable: bool,
+}
+
+impl GeneratedFile {
+    fn new(path: impl Into<String>, content: impl Into<String>) -> GeneratedFile {
+        GeneratedFile {
+            path: path.into(),
+            content: content.into(),
+            executable: false,
+        }
+    }
+
+    fn executable(path: impl Into<String>, content: impl Into<String>) -> GeneratedFile {
+        GeneratedFile {
+            executable: true,
+            ..GeneratedFile::new(path, content)
+        }
+    }
+}
+
+/// The files of the package, with `auth_token` written to `config.json`.
+pub fn generate(
+    spec: &PackageSpec,
+    auth_token: Option<&str>,
+) -> Result<Vec<GeneratedFile>, PackageError> {
+    let name = agents::check_name(&spec.name)?;
+    let install_dir = spec.install_dir.as_deref().unwrap_or(DEFAULT_REMOTE_DIR);
+    let schedule = spec.schedule.as_deref().unwrap_or(DEFAULT_SCHEDULE);
+    check_spec(spec, install_dir, schedule)?;
+
+    let config = ServerConfig {
+        agent_id: name,
+        mode: spec.mode,
+        port: spec.port,
+        auth_token,
+        max_concurrent_jobs: spec.max_concurrent_jobs,
+        max_queued_jobs: spec.max_queued_jobs,
+        job_timeout: spec.job_timeout,
+        allowed_modules: &spec.allowed_modules,
+    };
+    let config = serde_json::to_string_pretty(&config)
+        .map_err(|e| PackageError::InvalidSpec("config".to_string(), e.to_string()))?;
+
+    let mut files = match spec.deployment {
+        DeploymentType::Docker => docker_files(name, spec),
+        DeploymentType::Cron => cron_files(name, mode_name(spec.mode), schedule, install_dir),
+        DeploymentType::Systemd => systemd_files(name, install_dir),
+        DeploymentType::Shell => shell_files(name),
+    };
+    files.push(GeneratedFile::new("config.json", config + "\n"));
+    files.push(GeneratedFile::new("agent_server.py", AGENT_SERVER));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn check_spec(spec: &PackageSpec, install_dir: &str, schedule: &str) -> Result<(), PackageError> {
+    let invalid = |field: &str, message: &str| {
+        Err(PackageError::InvalidSpec(
+            field.to_string(),
+            message.to_string(),
+        ))
+    };
+    if spec.port == 0 {
+        return invalid("port", "must be between 1 and 65535");
+    }
+    if spec.max_concurrent_jobs == 0 {
+        return invalid("max_concurrent_jobs", "must be at least 1");
+    }
+    if spec.job_timeout == 0 {
+        return invalid("job_timeout", "must be at least 1 second");
+    }
+    // Both end up unquoted in the unit file, the crontab and shell commands.
+    if !install_dir.starts_with('/')
+        || install_dir
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '\'' | '"' | '\\' | '%'))
+    {
+        return invalid(
+            "install_dir",
+            "must be an absolute path without spaces, quotes or '%'",
+        );
+    }
+    if schedule.split_whitespace().count() != 5 || schedule.chars().any(|c| c.is_control()) {
+        return invalid("schedule", "must have five crontab fields");
+    }
+    if spec
+        .emit_target
+        .as_deref()
+        .is_some_and(|target| target.chars().any(|c| c.is_control()))
+    {
+        return invalid("emit_target", "must be a single line");
+    }
+    Ok(())
+}
+
+fn mode_name(mode: AgentMode) -> &'static str {
+    match mode {
+        AgentMode::Emit => "emit",
+        AgentMode::Serve => "serve",
+    }
+}
+
+fn docker_files(name: &str, spec: &PackageSpec) -> Vec<GeneratedFile> {
+    let port = spec.port;
+    let dockerfile = format!(
+        r#"FROM python:3.11-slim
+
+WORKDIR /app
+
+# Install dependencies
+COPY requirements.txt .
+RUN pip install --no-cache-dir -r requirements.txt
+
+# Copy agent code
+COPY agent_server.py .
+COPY config.json .
+
+# Health check
+HEALTHCHECK --interval=30s --timeout=5s --start-period=5s --retries=3 \
+  CMD python -c "import requests; requests.get('http://localhost:{port}/health', timeout=3)"
+
+EXPOSE {port}
+
+CMD ["python", "agent_server.py"]
+"#
+    );
+    let compose = format!(
+        r#"version: '3.8'
+
+services:
+  {name}:
+    build: .
+    container_name: {name}
+    restart: unless-stopped
+    ports:
+      - "{port}:{port}"
+    environment:
+      - AGENT_NAME={name}
+      - AGENT_MODE={mode}
+      - EMIT_TARGET={emit_target}
+    volumes:
+      - agent-data:/app/data
+    networks:
+      - apt-network
+
+volumes:
+  agent-data:
+
+networks:
+  apt-network:
+    driver: bridge
+"#,
+        mode = mode_name(spec.mode),
+        emit_target = spec.emit_target.as_deref().unwrap_or_default(),
+    );
+    let readme = format!(
+        r#"# Agent: {name}
+
+## Deployment Method: Docker
+
+### Build and Run
+
+```bash
+# Build image
+docker-compose build
+
+# Start agent
+docker-compose up -d
+
+# Check logs
+docker-compose logs -f
+
+# Stop agent
+docker-compose down
+```
+
+### Verify
+
+```bash
+curl http://localhost:{port}/health
+```
+"#
+    );
+    vec![
+        GeneratedFile::new("Dockerfile", dockerfile),
+        GeneratedFile::new("docker-compose.yml", compose),
+        GeneratedFile::new("requirements.txt", DOCKER_REQUIREMENTS),
+        GeneratedFile::new("README.md", readme),
+    ]
+}
+
+fn cron_files(name: &str, mode: &str, schedule: &str, install_dir: &str) -> Vec<GeneratedFile> {
+    // cron starts jobs in the home directory, so the server is pointed at
+    // its config explicitly.
+    let script = format!(
+        r#"#!/bin/bash
+# APT Agent: {name}
+# Mode: {mode}
+
+AGENT_DIR="$(cd "$(dirname "${{BASH_SOURCE[0]}}")" && pwd)"
+LOG_FILE="$AGENT_DIR/agent.log"
+export AGENT_CONFIG_PATH="$AGENT_DIR/config.json"
+
+# Source virtual environment if exists
+if [ -d "$AGENT_DIR/venv" ]; then
+    source "$AGENT_DIR/venv/bin/activate"
+fi
+
+# Execute agent
+python3 "$AGENT_DIR/agent_server.py" \
+    >> "$LOG_FILE" 2>&1
+
+# Cleanup old logs (keep last 7 days)
+find "$AGENT_DIR" -name "*.log" -mtime +7 -delete
+"#
+    );
+    let readme = format!(
+        r#"# Agent: {name}
+
+## Deployment Method: Cron
+
+### Setup
+
+```bash
+# Create virtual environment
+python3 -m venv venv
+source venv/bin/activate
+pip install -r requirements.txt
+
+# Install crontab
+crontab -l > /tmp/current_cron
+cat crontab.txt >> /tmp/current_cron
+crontab /tmp/current_cron
+rm /tmp/current_cron
+
+# Verify
+crontab -l | grep {name}
+```
+
+### Manual Run
+
+```bash
+./run_agent.sh
+```
+"#
+    );
+    vec![
+        GeneratedFile::executable("run_agent.sh", script),
+        GeneratedFile::new(
+            "crontab.txt",
+            format!("{schedule} {install_dir}/run_agent.sh\n"),
+        ),
+        GeneratedFile::new("requirements.txt", REQUIREMENTS),
+        GeneratedFile::new("README.md", readme),
+    ]
+}
+
+fn systemd_files(name: &str, install_dir: &str) -> Vec<GeneratedFile> {
+    let service = format!(
+        r#"[Unit]
+Description=APT Remote Agent - {name}
+After=network.target
+
+[Service]
+Type=simple
+User=apt-agent
+WorkingDirectory={install_dir}
+Environment="AGENT_CONFIG_PATH={install_dir}/config.json"
+ExecStart={install_dir}/venv/bin/python {install_dir}/agent_server.py
+Restart=always
+RestartSec=10
+
+[Install]
+WantedBy=multi-user.target
+"#
+    );
+    let install_script = format!(
+        r#"#!/bin/bash
+# Install APT Agent as systemd service
+
+set -e
+
+echo "Installing APT Agent: {name}"
+
+# Create virtual environment
+python3 -m venv venv
+source venv/bin/activate
+pip install -r requirements.txt
+
+# Create service user
+if ! id "apt-agent" &>/dev/null; then
+    sudo useradd -r -s /bin/false apt-agent
+fi
+
+# Set permissions
+sudo chown -R apt-agent:apt-agent .
+
+# Install service
+sudo cp {name}.service /etc/systemd/system/
+sudo systemctl daemon-reload
+sudo systemctl enable {name}
+sudo systemctl start {name}
+
+echo "✅ Agent installed and started"
+echo "Check status: sudo systemctl status {name}"
+"#
+    );
+    let readme = format!(
+        r#"# Agent: {name}
+
+## Deployment Method: Systemd
+
+### Install
+
+```bash
+sudo ./install.sh
+```
+
+### Manage
+
+```bash
+# Status
+sudo systemctl status {name}
+
+# Logs
+sudo journalctl -u {name} -f
+
+# Restart
+sudo systemctl restart {name}
+
+# Stop
+sudo systemctl stop {name}
+```
+"#
+    );
+    vec![
+        GeneratedFile::new(format!("{name}.service"), service),
+        GeneratedFile::executable("install.sh", install_script),
+        GeneratedFile::new("requirements.txt", REQUIREMENTS),
+        GeneratedFile::new("README.md", readme),
+    ]
+}
+
+fn shell_files(name: &str) -> Vec<GeneratedFile> {
+    let script = format!(
+        r#"#!/bin/bash
+# APT Agent: {name}
+
+cd "$(dirname "$0")"
+
+# Setup venv if needed
+if [ ! -d "venv" ]; then
+    python3 -m venv venv
+    source venv/bin/activate
+    pip install -r requirements.txt
+else
+    source venv/bin/activate
+fi
+
+# Run agent
+python agent_server.py
+"#
+    );
+    vec![
+        GeneratedFile::executable("start_agent.sh", script),
+        GeneratedFile::new("requirements.txt", REQUIREMENTS),
+    ]
+}
+
+/// Writes the files into `dir`, replacing files of the same name. Other
+/// files, such as the `venv` of a manual install, are left alone.
+pub fn write(files: &[GeneratedFile], dir: &Path) -> Result<(), PackageError> {
+    fs::create_dir_all(dir).map_err(|e| WorkspaceError::io(dir, e))?;
+    for file in files {
+        let path = dir.join(&file.path);
+        versions::atomic_write(&path, file.content.as_bytes())?;
+        set_mode(&path, mode(file))?;
+    }
+    Ok(())
+}
+
+/// A tar.gz of the files below a `<name>/` directory, as `tar czf` of the
+/// package directory would produce.
+pub fn bundle(name: &str, files: &[GeneratedFile]) -> Result<Vec<u8>, PackageError> {
+    let bundle_error = |e: std::io::Error| PackageError::Bundle(name.to_string(), e.to_string());
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_mode(0o755);
+    header.set_mtime(mtime);
+    header.set_size(0);
+    archive
+        .append_data(&mut header, format!("{name}/"), std::io::empty())
+        .map_err(bundle_error)?;
+    for file in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(mode(file));
+        header.set_mtime(mtime);
+        header.set_size(file.content.len() as u64);
+        archive
+            .append_data(
+                &mut header,
+                format!("{name}/{}", file.path),
+                file.content.as_bytes(),
+            )
+            .map_err(bundle_error)?;
+    }
+    let mut encoder = archive.into_inner().map_err(bundle_error)?;
+    encoder.flush().map_err(bundle_error)?;
+    encoder.finish().map_err(bundle_error)
+}
+
+/// `config.json` holds the auth token, so it is kept private.
+fn mode(file: &GeneratedFile) -> u32 {
+    if file.executable {
+        0o755
+    } else if file.path == "config.json" {
+        0o600
+    } else {
+        0o644
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), PackageError> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| WorkspaceError::io(path, e).into())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<(), PackageError> {
+    Ok(())
+}
+
+/// Default package location of `aptcli agent create`.
+pub fn default_package_dir(agent: &str) -> Option<PathBuf> {
+    tauri::api::path::home_dir().map(|home| home.join(".apt").join("agents").join(agent))
+}
+
+/// Generates the package with its auth token resolved like an agent's
+/// token for a run without a test file.
+fn generate_resolved(
+    workspace: &Workspace,
+    profiles: &ProfileStore,
+    vault: &Vault,
+    spec: &PackageSpec,
+) -> Result<Vec<GeneratedFile>, PackageError> {
+    let auth_token = match spec.auth_token.as_deref() {
+        Some(token) => {
+            let environment = Environment::load(workspace, profiles, None, None)?;
+            let token = environment
+                .expand(token)
+                .map_err(PackageError::UnsetVariable)?;
+            Some(vault.expand(&token)?)
+        }
+        None => None,
+    };
+    generate(spec, auth_token.as_deref())
+}
+
+#[tauri::command]
+pub fn preview_agent_package(spec: PackageSpec) -> Result<Vec<GeneratedFile>, PackageError> {
+    generate(&spec, spec.auth_token.as_deref())
+}
+
+/// Writes the package to `package_dir`, `~/.apt/agents/<name>` by default,
+/// and returns the directory.
+#[tauri::command]
+pub fn write_agent_package(
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    vault: tauri::State<'_, Vault>,
+    spec: PackageSpec,
+    package_dir: Option<PathBuf>,
+) -> Result<PathBuf, PackageError> {
+    let files = generate_resolved(&workspace, &profiles, &vault, &spec)?;
+    let dir = package_dir
+        .or_else(|| default_package_dir(spec.name.trim()))
+        .ok_or_else(|| {
+            PackageError::InvalidSpec("package_dir".to_string(), "no home directory".to_string())
+        })?;
+    write(&files, &dir)?;
+    Ok(dir)
+}
+
+/// Saves the package as a tar.gz bundle at `file_path`.
+#[tauri::command]
+pub fn export_agent_package(
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    vault: tauri::State<'_, Vault>,
+    spec: PackageSpec,
+    file_path: PathBuf,
+) -> Result<(), PackageError> {
+    let files = generate_resolved(&workspace, &profiles, &vault, &spec)?;
+    let bundle = bundle(spec.name.trim(), &files)?;
+    versions::atomic_write(&file_path, &bundle)?;
+    set_mode(&file_path, 0o600)
+}
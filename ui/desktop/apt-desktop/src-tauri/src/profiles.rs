@@ -0,0 +1,381 @@
+// Named environment profiles (dev, staging, prod, ...).
+//
+// A profile is a set of variables that `${VAR}` references in test
+// definitions resolve against, on top of the process environment and the
+// workspace `.env` file. The selected profile is also injected into the
+// environment of pytest and aptcli runs. Profiles are stored in
+// `profiles.json` under the app data directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::variables;
+use crate::versions;
+use crate::workspace::WorkspaceError;
+
+const PROFILES_FILE: &str = "profiles.json";
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error("profile '{0}' does not exist")]
+    NotFound(String),
+    #[error("profile '{0}' already exists")]
+    AlreadyExists(String),
+    #[error("invalid profile name '{0}'")]
+    InvalidName(String),
+    #[error("invalid variable name '{0}'")]
+    InvalidVariable(String),
+    #[error("variable '{0}' does not exist")]
+    VariableNotFound(String),
+}
+
+impl ProfileError {
+    fn kind(&self) -> &'static str {
+        match self {
+            ProfileError::Workspace(_) => "workspace",
+            ProfileError::NotFound(_) => "profile_not_found",
+            ProfileError::AlreadyExists(_) => "already_exists",
+            ProfileError::InvalidName(_) => "invalid_name",
+            ProfileError::InvalidVariable(_) => "invalid_variable",
+            ProfileError::VariableNotFound(_) => "variable_not_found",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// profile or variable name.
+impl Serialize for ProfileError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        match self {
+            ProfileError::Workspace(e) => e.serialize(serializer),
+            ProfileError::NotFound(name)
+            | ProfileError::AlreadyExists(name)
+            | ProfileError::InvalidName(name)
+            | ProfileError::InvalidVariable(name)
+            | ProfileError::VariableNotFound(name) => Repr {
+                kind: self.kind(),
+                message: self.to_string(),
+                path: Some(name),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProfileVariable {
+    pub value: String,
+    /// Secret values are masked whenever they are shown.
+    #[serde(default)]
+    pub secret: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub variables: IndexMap<String, ProfileVariable>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct ProfilesConfig {
+    selected: Option<String>,
+    #[serde(default)]
+    profiles: IndexMap<String, Profile>,
+}
+
+#[derive(Serialize)]
+pub struct VariableInfo {
+    pub name: String,
+    /// `None` for secrets; their values never leave the backend.
+    pub value: Option<String>,
+    pub secret: bool,
+}
+
+#[derive(Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub selected: bool,
+    pub variables: Vec<VariableInfo>,
+}
+
+pub struct ProfileStore {
+    config: RwLock<ProfilesConfig>,
+    path: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn load(data_dir: &Path) -> ProfileStore {
+        let path = data_dir.join(PROFILES_FILE);
+        let config = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        ProfileStore {
+            config: RwLock::new(config),
+            path,
+        }
+    }
+
+    pub fn selected(&self) -> Option<String> {
+        self.config.read().unwrap().selected.clone()
+    }
+
+    pub fn get(&self, name: &str) -> Result<Profile, ProfileError> {
+        self.config
+            .read()
+            .unwrap()
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ProfileError::NotFound(name.to_string()))
+    }
+
+    pub fn list(&self) -> Vec<ProfileInfo> {
+        let config = self.config.read().unwrap();
+        config
+            .profiles
+            .iter()
+            .map(|(name, profile)| ProfileInfo {
+                name: name.clone(),
+                selected: config.selected.as_ref() == Some(name),
+                variables: profile
+                    .variables
+                    .iter()
+                    .map(|(name, variable)| VariableInfo {
+                        name: name.clone(),
+                        value: (!variable.secret).then(|| variable.value.clone()),
+                        secret: variable.secret,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Creates an empty profile, or a copy of `copy_from`.
+    pub fn create(&self, name: &str, copy_from: Option<&str>) -> Result<(), ProfileError> {
+        let name = check_name(name)?;
+        self.update(|config| {
+            if config.profiles.contains_key(name) {
+                return Err(ProfileError::AlreadyExists(name.to_string()));
+            }
+            let profile = match copy_from {
+                Some(source) => profile(config, source)?.clone(),
+                None => Profile::default(),
+            };
+            config.profiles.insert(name.to_string(), profile);
+            Ok(())
+        })
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<(), ProfileError> {
+        let new_name = check_name(new_name)?;
+        self.update(|config| {
+            if name == new_name {
+                return profile(config, name).map(|_| ());
+            }
+            if config.profiles.contains_key(new_name) {
+                return Err(ProfileError::AlreadyExists(new_name.to_string()));
+            }
+            let index = config
+                .profiles
+                .get_index_of(name)
+                .ok_or_else(|| ProfileError::NotFound(name.to_string()))?;
+            let (_, profile) = config.profiles.shift_remove_index(index).unwrap();
+            config
+                .profiles
+                .shift_insert(index, new_name.to_string(), profile);
+            if config.selected.as_deref() == Some(name) {
+                config.selected = Some(new_name.to_string());
+            }
+            Ok(())
+        })
+    }
+
+    /// Deletes a profile; deleting the selected profile clears the selection.
+    pub fn delete(&self, name: &str) -> Result<(), ProfileError> {
+        self.update(|config| {
+            config
+                .profiles
+                .shift_remove(name)
+                .ok_or_else(|| ProfileError::NotFound(name.to_string()))?;
+            if config.selected.as_deref() == Some(name) {
+                config.selected = None;
+            }
+            Ok(())
+        })
+    }
+
+    /// Selects the profile used for runs; `None` runs without one.
+    pub fn select(&self, name: Option<&str>) -> Result<(), ProfileError> {
+        self.update(|config| {
+            if let Some(name) = name {
+                profile(config, name)?;
+            }
+            config.selected = name.map(str::to_string);
+            Ok(())
+        })
+    }
+
+    /// Adds or updates a variable. A `None` value keeps the current one, so
+    /// a secret can be re-flagged without sending it back from the UI.
+    pub fn set_variable(
+        &self,
+        profile_name: &str,
+        name: &str,
+        value: Option<String>,
+        secret: bool,
+    ) -> Result<(), ProfileError> {
+        if !variables::is_variable_name(name) {
+            return Err(ProfileError::InvalidVariable(name.to_string()));
+        }
+        self.update(|config| {
+            let profile = profile_mut(config, profile_name)?;
+            let value = match (value, profile.variables.get(name)) {
+                (Some(value), _) => value,
+                (None, Some(current)) => current.value.clone(),
+                (None, None) => return Err(ProfileError::VariableNotFound(name.to_string())),
+            };
+            profile
+                .variables
+                .insert(name.to_string(), ProfileVariable { value, secret });
+            Ok(())
+        })
+    }
+
+    pub fn remove_variable(&self, profile_name: &str, name: &str) -> Result<(), ProfileError> {
+        self.update(|config| {
+            profile_mut(config, profile_name)?
+                .variables
+                .shift_remove(name)
+                .map(|_| ())
+                .ok_or_else(|| ProfileError::VariableNotFound(name.to_string()))
+        })
+    }
+
+    /// Applies `change` and saves the result; nothing is changed in memory
+    /// when either step fails.
+    fn update(
+        &self,
+        change: impl FnOnce(&mut ProfilesConfig) -> Result<(), ProfileError>,
+    ) -> Result<(), ProfileError> {
+        let mut config = self.config.write().unwrap();
+        let mut updated = config.clone();
+        change(&mut updated)?;
+
+        let content = serde_json::to_string_pretty(&updated)
+            .map_err(|e| WorkspaceError::InvalidPath(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| WorkspaceError::io(dir, e))?;
+        }
+        versions::atomic_write(&self.path, content.as_bytes())?;
+        // Secret values are stored in the file; keep it private to the user.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| WorkspaceError::io(&self.path, e))?;
+        }
+
+        *config = updated;
+        Ok(())
+    }
+}
+
+fn profile<'a>(config: &'a ProfilesConfig, name: &str) -> Result<&'a Profile, ProfileError> {
+    config
+        .profiles
+        .get(name)
+        .ok_or_else(|| ProfileError::NotFound(name.to_string()))
+}
+
+fn profile_mut<'a>(
+    config: &'a mut ProfilesConfig,
+    name: &str,
+) -> Result<&'a mut Profile, ProfileError> {
+    config
+        .profiles
+        .get_mut(name)
+        .ok_or_else(|| ProfileError::NotFound(name.to_string()))
+}
+
+fn check_name(name: &str) -> Result<&str, ProfileError> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() || trimmed.len() > MAX_NAME_LEN || trimmed.contains(char::is_control) {
+        return Err(ProfileError::InvalidName(name.to_string()));
+    }
+    Ok(trimmed)
+}
+
+#[tauri::command]
+pub fn list_profiles(profiles: tauri::State<'_, ProfileStore>) -> Vec<ProfileInfo> {
+    profiles.list()
+}
+
+#[tauri::command]
+pub fn create_profile(
+    profiles: tauri::State<'_, ProfileStore>,
+    name: String,
+    copy_from: Option<String>,
+) -> Result<(), ProfileError> {
+    profiles.create(&name, copy_from.as_deref())
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    profiles: tauri::State<'_, ProfileStore>,
+    name: String,
+    new_name: String,
+) -> Result<(), ProfileError> {
+    profiles.rename(&name, &new_name)
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    profiles: tauri::State<'_, ProfileStore>,
+    name: String,
+) -> Result<(), ProfileError> {
+    profiles.delete(&name)
+}
+
+#[tauri::command]
+pub fn select_profile(
+    profiles: tauri::State<'_, ProfileStore>,
+    name: Option<String>,
+) -> Result<(), ProfileError> {
+    profiles.select(name.as_deref())
+}
+
+#[tauri::command]
+pub fn set_profile_variable(
+    profiles: tauri::State<'_, ProfileStore>,
+    profile: String,
+    name: String,
+    value: Option<String>,
+    secret: bool,
+) -> Result<(), ProfileError> {
+    profiles.set_variable(&profile, &name, value, secret)
+}
+
+#[tauri::command]
+pub fn remove_profile_variable(
+    profiles: tauri::State<'_, ProfileStore>,
+    profile: String,
+    name: String,
+) -> Result<(), ProfileError> {
+    profiles.remove_variable(&profile, &name)
+}
@@ -0,0 +1,487 @@
+// Execution of pytest/aptcli child processes.
+//
+// Every run is tracked in the managed `RunRegistry` under its run id so the
+// frontend can list, cancel and wait on it. Output can additionally be
+// forwarded line by line as window events, which lets the Test Runner page
+// show progress of long k6/JMeter runs as they happen.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tauri::Window;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::watch;
+
+use crate::junit::{self, TestReport, TestSummary};
+
+pub const RUN_OUTPUT_EVENT: &str = "run-output";
+pub const RUN_FINISHED_EVENT: &str = "run-finished";
+
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+const MAX_FINISHED_RUNS: usize = 50;
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RunOutputEvent {
+    pub run_id: String,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RunFinishedEvent {
+    pub run_id: String,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub success: bool,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+    pub tests: Option<TestSummary>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    Running,
+    Cancelling,
+    Finished,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RunInfo {
+    pub run_id: String,
+    pub command: Vec<String>,
+    pub pid: Option<u32>,
+    pub started_at: DateTime<Utc>,
+    pub state: RunState,
+}
+
+/// Final status and output of a child process.
+///
+/// `success` is only true for a zero exit code of a run that was not
+/// cancelled, so callers never have to interpret the raw output.
+#[derive(Clone, Serialize)]
+pub struct RunResult {
+    pub run_id: String,
+    pub command: Vec<String>,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub cancelled: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub tests: Option<TestReport>,
+    pub error: Option<String>,
+}
+
+struct RunEntry {
+    command: Vec<String>,
+    pid: Option<u32>,
+    started_at: DateTime<Utc>,
+    cancel_requested: Arc<AtomicBool>,
+    finished: watch::Receiver<Option<RunResult>>,
+}
+
+impl RunEntry {
+    fn info(&self, run_id: &str) -> RunInfo {
+        let state = match &*self.finished.borrow() {
+            Some(result) if result.cancelled => RunState::Cancelled,
+            Some(_) => RunState::Finished,
+            None if self.cancel_requested.load(Ordering::SeqCst) => RunState::Cancelling,
+            None => RunState::Running,
+        };
+
+        RunInfo {
+            run_id: run_id.to_string(),
+            command: self.command.clone(),
+            pid: self.pid,
+            started_at: self.started_at,
+            state,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished.borrow().is_some()
+    }
+}
+
+/// Registry of child processes started from the frontend, keyed by run id.
+#[derive(Default)]
+pub struct RunRegistry {
+    runs: Mutex<HashMap<String, RunEntry>>,
+}
+
+impl RunRegistry {
+    /// Spawns `command` in its own process group and tracks it under `run_id`.
+    ///
+    /// When `window` is given, output lines and the final status are emitted as
+    /// `run-output`/`run-finished` events; the full output is always collected
+    /// into the `RunResult` returned by `wait`. If `junit_xml` is set, the
+    /// report the command writes there is parsed into `RunResult::tests` and
+    /// the file removed afterwards.
+    pub fn spawn(
+        &self,
+        run_id: String,
+        mut command: Command,
+        window: Option<Window>,
+        junit_xml: Option<PathBuf>,
+    ) -> Result<(), String> {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.contains_key(&run_id) {
+            return Err(format!("run {} already exists", run_id));
+        }
+
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let command_line = command_line(&command);
+        let started = Instant::now();
+        let started_at = Utc::now();
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("failed to start {}: {}", command_line[0], e))?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        let cancel_requested = Arc::new(AtomicBool::new(false));
+        let (finished_tx, finished_rx) = watch::channel(None);
+
+        prune_finished(&mut runs);
+        runs.insert(
+            run_id.clone(),
+            RunEntry {
+                command: command_line.clone(),
+                pid: child.id(),
+                started_at,
+                cancel_requested: cancel_requested.clone(),
+                finished: finished_rx,
+            },
+        );
+        drop(runs);
+
+        tauri::async_runtime::spawn(async move {
+            let out_task = stdout.map(|s| {
+                tauri::async_runtime::spawn(collect_lines(
+                    window.clone(),
+                    run_id.clone(),
+                    OutputStream::Stdout,
+                    s,
+                ))
+            });
+            let err_task = stderr.map(|s| {
+                tauri::async_runtime::spawn(collect_lines(
+                    window.clone(),
+                    run_id.clone(),
+                    OutputStream::Stderr,
+                    s,
+                ))
+            });
+
+            let status = child.wait().await;
+
+            // Drain the pipes before reporting completion so no trailing line
+            // arrives after the finished event.
+            let stdout = match out_task {
+                Some(task) => task.await.unwrap_or_default(),
+                None => String::new(),
+            };
+            let stderr = match err_task {
+                Some(task) => task.await.unwrap_or_default(),
+                None => String::new(),
+            };
+
+            let (exit_code, signal, error) = match status {
+                Ok(status) => (status.code(), exit_signal(&status), None),
+                Err(e) => (None, None, Some(e.to_string())),
+            };
+            let cancelled = cancel_requested.load(Ordering::SeqCst);
+
+            let tests = junit_xml.and_then(|path| {
+                let report = junit::read_report(&path).ok();
+                let _ = std::fs::remove_file(&path);
+                report
+            });
+
+            let result = RunResult {
+                run_id,
+                command: command_line,
+                success: exit_code == Some(0) && !cancelled,
+                exit_code,
+                signal,
+                cancelled,
+                stdout,
+                stderr,
+                started_at,
+                finished_at: Utc::now(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                tests,
+                error,
+            };
+
+            if let Some(window) = &window {
+                let _ = window.emit(
+                    RUN_FINISHED_EVENT,
+                    RunFinishedEvent {
+                        run_id: result.run_id.clone(),
+                        exit_code: result.exit_code,
+                        signal: result.signal,
+                        success: result.success,
+                        elapsed_ms: result.duration_ms,
+                        cancelled: result.cancelled,
+                        tests: result.tests.as_ref().map(|report| report.summary.clone()),
+                        error: result.error.clone(),
+                    },
+                );
+            }
+
+            let _ = finished_tx.send(Some(result));
+        });
+
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<RunInfo> {
+        let runs = self.runs.lock().unwrap();
+        let mut infos: Vec<RunInfo> = runs.iter().map(|(id, entry)| entry.info(id)).collect();
+        infos.sort_by_key(|info| std::cmp::Reverse(info.started_at));
+        infos
+    }
+
+    /// Waits for a run to exit, giving up after `timeout` if one is given.
+    pub async fn wait(&self, run_id: &str, timeout: Option<Duration>) -> Result<RunResult, String> {
+        let finished = self.receiver(run_id)?;
+
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, wait_finished(finished))
+                .await
+                .map_err(|_| format!("run {} is still running", run_id))?,
+            None => wait_finished(finished).await,
+        }
+    }
+
+    /// Stops a run by sending SIGTERM to its process group, escalating to
+    /// SIGKILL if it has not exited after `grace`.
+    pub async fn cancel(&self, run_id: &str, grace: Duration) -> Result<RunResult, String> {
+        let (pid, finished) = {
+            let runs = self.runs.lock().unwrap();
+            let entry = runs
+                .get(run_id)
+                .ok_or_else(|| format!("unknown run {}", run_id))?;
+            if !entry.is_finished() {
+                entry.cancel_requested.store(true, Ordering::SeqCst);
+            }
+            (entry.pid, entry.finished.clone())
+        };
+
+        if finished.borrow().is_some() {
+            return wait_finished(finished).await;
+        }
+
+        if let Some(pid) = pid {
+            terminate_group(pid).map_err(|e| e.to_string())?;
+
+            if tokio::time::timeout(grace, wait_finished(finished.clone()))
+                .await
+                .is_err()
+            {
+                kill_group(pid).map_err(|e| e.to_string())?;
+            }
+        }
+
+        wait_finished(finished).await
+    }
+
+    fn receiver(&self, run_id: &str) -> Result<watch::Receiver<Option<RunResult>>, String> {
+        self.runs
+            .lock()
+            .unwrap()
+            .get(run_id)
+            .map(|entry| entry.finished.clone())
+            .ok_or_else(|| format!("unknown run {}", run_id))
+    }
+}
+
+pub fn new_run_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+#[tauri::command]
+pub fn list_runs(registry: tauri::State<'_, RunRegistry>) -> Vec<RunInfo> {
+    registry.list()
+}
+
+#[tauri::command]
+pub async fn cancel_run(
+    registry: tauri::State<'_, RunRegistry>,
+    run_id: String,
+    grace_ms: Option<u64>,
+) -> Result<RunResult, String> {
+    let grace = grace_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_GRACE_PERIOD);
+    registry.cancel(&run_id, grace).await
+}
+
+#[tauri::command]
+pub async fn wait_run(
+    registry: tauri::State<'_, RunRegistry>,
+    run_id: String,
+    timeout_ms: Option<u64>,
+) -> Result<RunResult, String> {
+    registry
+        .wait(&run_id, timeout_ms.map(Duration::from_millis))
+        .await
+}
+
+async fn wait_finished(
+    mut finished: watch::Receiver<Option<RunResult>>,
+) -> Result<RunResult, String> {
+    let result = finished
+        .wait_for(|result| result.is_some())
+        .await
+        .map_err(|_| "run finished without reporting a result".to_string())?;
+    Ok(result.clone().unwrap())
+}
+
+fn prune_finished(runs: &mut HashMap<String, RunEntry>) {
+    let mut finished: Vec<(String, DateTime<Utc>)> = runs
+        .iter()
+        .filter(|(_, entry)| entry.is_finished())
+        .map(|(id, entry)| (id.clone(), entry.started_at))
+        .collect();
+
+    if finished.len() < MAX_FINISHED_RUNS {
+        return;
+    }
+
+    finished.sort_by_key(|(_, started)| *started);
+    for (id, _) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_RUNS) {
+        runs.remove(id);
+    }
+}
+
+fn command_line(command: &Command) -> Vec<String> {
+    let command = command.as_std();
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &std::process::ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(unix)]
+fn terminate_group(pid: u32) -> std::io::Result<()> {
+    signal_group(pid, libc::SIGTERM)
+}
+
+#[cfg(unix)]
+fn kill_group(pid: u32) -> std::io::Result<()> {
+    signal_group(pid, libc::SIGKILL)
+}
+
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // The child was started with `process_group(0)`, so its pid is also the
+    // process group id and this reaches k6/JMeter processes spawned by pytest.
+    if unsafe { libc::killpg(pid as libc::pid_t, signal) } == 0 {
+        return Ok(());
+    }
+
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::ESRCH) {
+        // The group already exited between the check and the signal.
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(windows)]
+fn terminate_group(pid: u32) -> std::io::Result<()> {
+    // Console processes on Windows have no graceful equivalent of SIGTERM.
+    kill_group(pid)
+}
+
+#[cfg(windows)]
+fn kill_group(pid: u32) -> std::io::Result<()> {
+    std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|_| ())
+}
+
+async fn collect_lines<R>(
+    window: Option<Window>,
+    run_id: String,
+    stream: OutputStream,
+    reader: R,
+) -> String
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    let mut collected = String::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buf)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string();
+                collected.push_str(&line);
+                collected.push('\n');
+
+                if let Some(window) = &window {
+                    let _ = window.emit(
+                        RUN_OUTPUT_EVENT,
+                        RunOutputEvent {
+                            run_id: run_id.clone(),
+                            stream,
+                            line,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    collected
+}
@@ -0,0 +1,423 @@
+// SSH sessions to agent hosts.
+//
+// Deploys run their steps through the `RemoteShell` trait, which an SSH
+// session implements. Like `ssh` itself, authentication uses the agent's key
+// file when one is set, else the keys of the SSH agent and the default
+// `~/.ssh/id_*` files. Host keys are checked against `~/.ssh/known_hosts`;
+// hosts not listed there are trusted on first use and remembered in the
+// app's own `known_hosts`, and a changed key is always refused.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use russh::client::{self, Handle, Msg};
+use russh::keys::{self, HashAlg, PrivateKeyWithHashAlg, PublicKey};
+use russh::{Channel, ChannelMsg, Disconnect};
+use serde::{Serialize, Serializer};
+
+use crate::agents::SshTarget;
+
+const KNOWN_HOSTS_FILE: &str = "known_hosts";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Wide enough that commands on a pseudo-terminal do not cut lines short.
+const TERMINAL_COLUMNS: u32 = 1000;
+/// Tried in this order when the agent has no key file, as `ssh` does.
+const DEFAULT_KEY_FILES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SshError {
+    #[error("could not connect to {0}: {1}")]
+    Connect(String, String),
+    #[error("timed out connecting to {0}")]
+    Timeout(String),
+    #[error("the host key of {0} has changed; remove line {2} of {1} if that is expected")]
+    HostKeyChanged(String, String, usize),
+    #[error("could not check the host key of {0}: {1}")]
+    KnownHosts(String, String),
+    #[error("cannot use key file {0}: {1}")]
+    KeyFile(String, String),
+    #[error("{0} refused every key that was offered")]
+    AuthFailed(String),
+    #[error("SSH session with {0} failed: {1}")]
+    Session(String, String),
+}
+
+impl SshError {
+    fn kind(&self) -> &'static str {
+        match self {
+            SshError::Connect(..) => "unreachable",
+            SshError::Timeout(_) => "timeout",
+            SshError::HostKeyChanged(..) => "host_key_changed",
+            SshError::KnownHosts(..) => "known_hosts",
+            SshError::KeyFile(..) => "key_file",
+            SshError::AuthFailed(_) => "unauthorized",
+            SshError::Session(..) => "session",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// SSH target, or the key file.
+impl Serialize for SshError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            SshError::Connect(path, _)
+            | SshError::Timeout(path)
+            | SshError::HostKeyChanged(path, ..)
+            | SshError::KnownHosts(path, _)
+            | SshError::KeyFile(path, _)
+            | SshError::AuthFailed(path)
+            | SshError::Session(path, _) => path,
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path: Some(path),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CommandOutput {
+    /// `None` when the command was killed by a signal.
+    pub exit_status: Option<u32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.exit_status == Some(0)
+    }
+}
+
+/// Runs commands on an agent host.
+pub trait RemoteShell {
+    /// Runs `command` through the login shell of the remote user, with
+    /// `stdin` as its input, and waits for it to exit.
+    fn exec(
+        &mut self,
+        command: &str,
+        stdin: &[u8],
+    ) -> impl Future<Output = Result<CommandOutput, SshError>> + Send;
+}
+
+/// Opens SSH sessions; shared by all deploys.
+pub struct SshClient {
+    known_hosts: PathBuf,
+}
+
+impl SshClient {
+    pub fn new(data_dir: &Path) -> SshClient {
+        SshClient {
+            known_hosts: data_dir.join(KNOWN_HOSTS_FILE),
+        }
+    }
+
+    pub async fn connect(&self, target: &SshTarget) -> Result<SshSession, SshError> {
+        let name = display_target(target);
+        let config = Arc::new(client::Config {
+            keepalive_interval: Some(Duration::from_secs(30)),
+            ..Default::default()
+        });
+        let verdict = Arc::new(Mutex::new(None));
+        let handler = HostKeyCheck {
+            host: target.host.clone(),
+            port: target.port,
+            known_hosts: self.known_hosts.clone(),
+            verdict: verdict.clone(),
+        };
+
+        let connect = client::connect(config, (target.host.as_str(), target.port), handler);
+        let mut handle = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+            Ok(Ok(handle)) => handle,
+            Ok(Err(e)) => {
+                // A refused host key surfaces as a generic error; the reason
+                // was recorded during the check.
+                return Err(match verdict.lock().unwrap().take() {
+                    Some(HostKeyVerdict::Changed(file, line)) => {
+                        SshError::HostKeyChanged(name, file.display().to_string(), line)
+                    }
+                    Some(HostKeyVerdict::Unreadable(e)) => SshError::KnownHosts(name, e),
+                    None => SshError::Connect(name, e.to_string()),
+                });
+            }
+            Err(_) => return Err(SshError::Timeout(name)),
+        };
+
+        authenticate(&mut handle, target, &name).await?;
+        Ok(SshSession { handle, name })
+    }
+}
+
+pub struct SshSession {
+    handle: Handle<HostKeyCheck>,
+    name: String,
+}
+
+impl SshSession {
+    pub async fn close(self) {
+        let _ = self
+            .handle
+            .disconnect(Disconnect::ByApplication, "", "en")
+            .await;
+    }
+
+    /// Starts `command` and returns its output as it arrives, for commands
+    /// that run until they are stopped, such as `tail -F`. The command runs
+    /// on a pseudo-terminal, as with `ssh -t`, so that it is hung up when
+    /// the stream is closed; its stderr is merged into the output.
+    pub async fn stream(&mut self, command: &str) -> Result<CommandStream, SshError> {
+        let channel = self
+            .handle
+            .channel_open_session()
+            .await
+            .map_err(|e| self.session_error(e))?;
+        channel
+            .request_pty(false, "dumb", TERMINAL_COLUMNS, 0, 0, 0, &[])
+            .await
+            .map_err(|e| self.session_error(e))?;
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| self.session_error(e))?;
+        Ok(CommandStream {
+            channel,
+            exit_status: None,
+        })
+    }
+
+    fn session_error(&self, error: russh::Error) -> SshError {
+        SshError::Session(self.name.clone(), error.to_string())
+    }
+}
+
+/// Output of a command started with `SshSession::stream`.
+pub struct CommandStream {
+    channel: Channel<Msg>,
+    exit_status: Option<u32>,
+}
+
+impl CommandStream {
+    /// The next piece of output, or `None` once the command has exited.
+    /// Cancel-safe, so it can be raced against a stop signal.
+    pub async fn next(&mut self) -> Option<Vec<u8>> {
+        while let Some(message) = self.channel.wait().await {
+            match message {
+                ChannelMsg::Data { data } | ChannelMsg::ExtendedData { data, .. } => {
+                    return Some(data.to_vec())
+                }
+                ChannelMsg::ExitStatus { exit_status } => self.exit_status = Some(exit_status),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// `None` while the command runs, or when it was killed by a signal.
+    pub fn exit_status(&self) -> Option<u32> {
+        self.exit_status
+    }
+
+    pub async fn close(self) {
+        let _ = self.channel.close().await;
+    }
+}
+
+impl RemoteShell for SshSession {
+    async fn exec(&mut self, command: &str, stdin: &[u8]) -> Result<CommandOutput, SshError> {
+        let mut channel = self
+            .handle
+            .channel_open_session()
+            .await
+            .map_err(|e| self.session_error(e))?;
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| self.session_error(e))?;
+        if !stdin.is_empty() {
+            channel
+                .data(stdin)
+                .await
+                .map_err(|e| self.session_error(e))?;
+        }
+        channel.eof().await.map_err(|e| self.session_error(e))?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_status = None;
+        while let Some(message) = channel.wait().await {
+            match message {
+                ChannelMsg::Data { data } => stdout.extend_from_slice(&data),
+                ChannelMsg::ExtendedData { data, ext: 1 } => stderr.extend_from_slice(&data),
+                ChannelMsg::ExitStatus {
+                    exit_status: status,
+                } => exit_status = Some(status),
+                _ => {}
+            }
+        }
+        Ok(CommandOutput {
+            exit_status,
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        })
+    }
+}
+
+/// Quotes `value` as one word for a POSIX shell.
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `user@host:port`, for messages.
+pub fn display_target(target: &SshTarget) -> String {
+    format!("{}@{}:{}", target.user, target.host, target.port)
+}
+
+enum HostKeyVerdict {
+    Changed(PathBuf, usize),
+    Unreadable(String),
+}
+
+struct HostKeyCheck {
+    host: String,
+    port: u16,
+    known_hosts: PathBuf,
+    verdict: Arc<Mutex<Option<HostKeyVerdict>>>,
+}
+
+impl HostKeyCheck {
+    fn check(&self, key: &PublicKey) -> Result<(), HostKeyVerdict> {
+        let user_file = tauri::api::path::home_dir().map(|home| home.join(".ssh/known_hosts"));
+        for file in user_file.iter().chain([&self.known_hosts]) {
+            match keys::check_known_hosts_path(&self.host, self.port, key, file) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(keys::Error::KeyChanged { line }) => {
+                    return Err(HostKeyVerdict::Changed(file.clone(), line))
+                }
+                Err(e) => return Err(HostKeyVerdict::Unreadable(e.to_string())),
+            }
+        }
+        keys::known_hosts::learn_known_hosts_path(&self.host, self.port, key, &self.known_hosts)
+            .map_err(|e| HostKeyVerdict::Unreadable(e.to_string()))
+    }
+}
+
+impl client::Handler for HostKeyCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &keys::PublicKeyOrCertificate,
+    ) -> Result<bool, Self::Error> {
+        let key = match server_public_key {
+            keys::PublicKeyOrCertificate::PublicKey { key, .. } => key.clone(),
+            keys::PublicKeyOrCertificate::Certificate(certificate) => {
+                PublicKey::new(certificate.public_key().clone(), "")
+            }
+        };
+        match self.check(&key) {
+            Ok(()) => Ok(true),
+            Err(verdict) => {
+                *self.verdict.lock().unwrap() = Some(verdict);
+                Ok(false)
+            }
+        }
+    }
+}
+
+async fn authenticate(
+    handle: &mut Handle<HostKeyCheck>,
+    target: &SshTarget,
+    name: &str,
+) -> Result<(), SshError> {
+    let session_error = |e: russh::Error| SshError::Session(name.to_string(), e.to_string());
+    let rsa_hash = handle
+        .best_supported_rsa_hash()
+        .await
+        .map_err(session_error)?
+        .flatten();
+
+    if let Some(key_file) = &target.key_file {
+        let key = keys::load_secret_key(key_file, None)
+            .map_err(|e| SshError::KeyFile(key_file.display().to_string(), e.to_string()))?;
+        return if offer_key(handle, target, key, rsa_hash).await? {
+            Ok(())
+        } else {
+            Err(SshError::AuthFailed(name.to_string()))
+        };
+    }
+
+    #[cfg(unix)]
+    if offer_agent_keys(handle, target, rsa_hash).await {
+        return Ok(());
+    }
+
+    let ssh_dir = tauri::api::path::home_dir().map(|home| home.join(".ssh"));
+    for file in DEFAULT_KEY_FILES {
+        let Some(path) = ssh_dir.as_ref().map(|dir| dir.join(file)) else {
+            break;
+        };
+        // Missing and passphrase-protected default keys are skipped, as
+        // `ssh -o BatchMode=yes` does.
+        let Ok(key) = keys::load_secret_key(&path, None) else {
+            continue;
+        };
+        if offer_key(handle, target, key, rsa_hash).await? {
+            return Ok(());
+        }
+    }
+    Err(SshError::AuthFailed(name.to_string()))
+}
+
+async fn offer_key(
+    handle: &mut Handle<HostKeyCheck>,
+    target: &SshTarget,
+    key: keys::PrivateKey,
+    rsa_hash: Option<HashAlg>,
+) -> Result<bool, SshError> {
+    let key = PrivateKeyWithHashAlg::new(Arc::new(key), rsa_hash);
+    handle
+        .authenticate_publickey(&target.user, key)
+        .await
+        .map(|result| result.success())
+        .map_err(|e| SshError::Session(display_target(target), e.to_string()))
+}
+
+/// Offers the keys of the SSH agent named by `SSH_AUTH_SOCK`, if one runs.
+#[cfg(unix)]
+async fn offer_agent_keys(
+    handle: &mut Handle<HostKeyCheck>,
+    target: &SshTarget,
+    rsa_hash: Option<HashAlg>,
+) -> bool {
+    let Ok(mut agent) = keys::agent::client::AgentClient::connect_env().await else {
+        return false;
+    };
+    let Ok(identities) = agent.request_identities().await else {
+        return false;
+    };
+    for identity in identities {
+        let key = identity.public_key().into_owned();
+        if let Ok(result) = handle
+            .authenticate_publickey_with(&target.user, key, rsa_hash, &mut agent)
+            .await
+        {
+            if result.success() {
+                return true;
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,359 @@
+// Semantic checks on unified YAML test definitions.
+//
+// Problems are reported at the exact line and column the editor should
+// highlight instead of surfacing halfway through a Python run.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use yaml_rust2::scanner::ScanError;
+
+use crate::workspace::{Workspace, WorkspaceError};
+use crate::yaml_tree::{self, Node, Position};
+
+const PRIORITIES: &[&str] = &["urgent", "high", "normal", "low"];
+const DURATION_UNITS: &[&str] = &["ns", "us", "µs", "ms", "s", "m", "h"];
+const THRESHOLD_AGGREGATIONS: &[&str] = &["avg", "min", "max", "med", "count", "rate", "value"];
+// Longest first, so `<=` is not read as `<` followed by `=`.
+const THRESHOLD_OPERATORS: &[&str] = &["===", "==", "!=", "<=", ">=", "<", ">"];
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Dotted location of the offending node, e.g.
+    /// `workflows.load_test.steps[2].agent`.
+    pub path: String,
+    /// 1-based.
+    pub line: usize,
+    /// 1-based, in characters.
+    pub column: usize,
+}
+
+struct Validator<'a> {
+    workspace: &'a Workspace,
+    /// Directory of the definition file; relative `code_file` paths are
+    /// resolved against it, as the Python loader does.
+    base_dir: Option<&'a Path>,
+    agents: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, node: &Node, path: &str, message: String) {
+        self.report(Severity::Error, node, path, message);
+    }
+
+    fn report(&mut self, severity: Severity, node: &Node, path: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            path: path.to_string(),
+            line: node.position.line,
+            column: node.position.column,
+        });
+    }
+
+    fn check_definition(&mut self, root: &Node) {
+        if let Some(agents) = root.get("agents") {
+            self.agents = agents
+                .entries()
+                .filter_map(|(key, _)| key.as_str().map(str::to_string))
+                .collect();
+        }
+
+        if let Some(tests) = root.get("k6_tests") {
+            for (name, test) in tests.entries() {
+                let path = format!("k6_tests.{}", name.as_str().unwrap_or_default());
+                self.check_k6_config(test, &path);
+            }
+        }
+
+        if let Some(workflows) = root.get("workflows") {
+            for (name, workflow) in workflows.entries() {
+                let path = format!("workflows.{}", name.as_str().unwrap_or_default());
+                let Some(steps) = workflow.get("steps") else {
+                    continue;
+                };
+                for (index, step) in steps.items().iter().enumerate() {
+                    self.check_step(step, &format!("{}.steps[{}]", path, index));
+                }
+            }
+        }
+    }
+
+    fn check_k6_config(&mut self, config: &Node, path: &str) {
+        let Some(options) = config.get("options") else {
+            return;
+        };
+        let path = format!("{}.options", path);
+
+        if let Some(duration) = options.get("duration") {
+            self.check_duration(duration, &format!("{}.duration", path));
+        }
+        if let Some(stages) = options.get("stages") {
+            for (index, stage) in stages.items().iter().enumerate() {
+                if let Some(duration) = stage.get("duration") {
+                    self.check_duration(duration, &format!("{}.stages[{}].duration", path, index));
+                }
+            }
+        }
+
+        if let Some(thresholds) = options.get("thresholds") {
+            for (metric, expressions) in thresholds.entries() {
+                let path = format!(
+                    "{}.thresholds.{}",
+                    path,
+                    metric.as_str().unwrap_or_default()
+                );
+                for (index, expression) in expressions.items().iter().enumerate() {
+                    // Thresholds are either plain strings or objects with a
+                    // `threshold` key and abort settings.
+                    let node = expression.get("threshold").unwrap_or(expression);
+                    self.check_threshold(node, &format!("{}[{}]", path, index));
+                }
+            }
+        }
+    }
+
+    fn check_duration(&mut self, node: &Node, path: &str) {
+        if let Some(value) = node.as_str() {
+            if !is_valid_duration(value) {
+                self.error(
+                    node,
+                    path,
+                    format!(
+                        "invalid duration '{}'; expected a value such as 30s, 2m or 1h30m",
+                        value
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_threshold(&mut self, node: &Node, path: &str) {
+        match node.as_str() {
+            Some(value) if is_valid_threshold(value) => {}
+            Some(value) => self.error(
+                node,
+                path,
+                format!(
+                    "invalid threshold '{}'; expected an expression such as p(95)<500 or rate<0.01",
+                    value
+                ),
+            ),
+            None => self.error(node, path, "threshold must be a string".to_string()),
+        }
+    }
+
+    fn check_step(&mut self, step: &Node, path: &str) {
+        if let Some(agent) = step.get("agent") {
+            if let Some(name) = agent.as_str() {
+                if !self.agents.contains(name) {
+                    self.error(
+                        agent,
+                        &format!("{}.agent", path),
+                        format!("agent '{}' is not declared under agents", name),
+                    );
+                }
+            }
+        }
+
+        if let Some(priority) = step.get("priority") {
+            let value = priority.as_str().unwrap_or_default();
+            if !PRIORITIES.contains(&value) {
+                self.error(
+                    priority,
+                    &format!("{}.priority", path),
+                    format!(
+                        "invalid priority '{}'; expected one of {}",
+                        value,
+                        PRIORITIES.join(", ")
+                    ),
+                );
+            } else if step.get("agent").is_none() {
+                self.report(
+                    Severity::Warning,
+                    priority,
+                    &format!("{}.priority", path),
+                    "priority only applies to steps that run on an agent".to_string(),
+                );
+            }
+        }
+
+        let action = step.get("action");
+        if action.and_then(Node::as_str) == Some("api_call") {
+            let url = step.get("url").and_then(Node::as_str).unwrap_or_default();
+            if url.trim().is_empty() {
+                self.error(
+                    action.unwrap_or(step),
+                    path,
+                    "api_call step has no url".to_string(),
+                );
+            }
+        }
+
+        if let Some(config) = step.get("k6_config") {
+            self.check_k6_config(config, &format!("{}.k6_config", path));
+        }
+
+        // The loader ignores `code_file` when inline code is present.
+        if step.get("code").is_none() {
+            if let Some(code_file) = step.get("code_file") {
+                self.check_code_file(code_file, &format!("{}.code_file", path));
+            }
+        }
+    }
+
+    fn check_code_file(&mut self, node: &Node, path: &str) {
+        let Some(value) = node.as_str() else {
+            return;
+        };
+
+        let requested = match self.base_dir {
+            Some(dir) if Path::new(value).is_relative() => dir.join(value),
+            _ => value.into(),
+        };
+        let message = match self
+            .workspace
+            .resolve_existing(&requested.to_string_lossy())
+        {
+            Ok(resolved) if resolved.is_file() => return,
+            Ok(resolved) => format!("code_file {} is not a file", resolved.display()),
+            Err(WorkspaceError::NotFound(_)) => {
+                format!("code_file {} does not exist", requested.display())
+            }
+            Err(e) => format!("code_file {}", e),
+        };
+        self.error(node, path, message);
+    }
+}
+
+/// Accepts Go-style durations as used by k6 (`500ms`, `1m30s`, `1.5h`) and
+/// bare numbers, which k6 reads as milliseconds.
+fn is_valid_duration(value: &str) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    if value.parse::<f64>().is_ok() {
+        return true;
+    }
+
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        if rest[..number_len].parse::<f64>().is_err() {
+            return false;
+        }
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        if !DURATION_UNITS.contains(&&rest[..unit_len]) {
+            return false;
+        }
+        rest = &rest[unit_len..];
+    }
+    true
+}
+
+/// Checks the `<aggregation> <operator> <value>` form of k6 thresholds, e.g.
+/// `p(95)<500`, `avg <= 200` or `rate<0.01`.
+fn is_valid_threshold(value: &str) -> bool {
+    let Some((start, operator)) = THRESHOLD_OPERATORS
+        .iter()
+        .filter_map(|op| value.find(op).map(|index| (index, *op)))
+        .min_by_key(|(index, op)| (*index, std::cmp::Reverse(op.len())))
+    else {
+        return false;
+    };
+
+    let aggregation = value[..start].trim();
+    let threshold = value[start + operator.len()..].trim();
+
+    let aggregation_valid = THRESHOLD_AGGREGATIONS.contains(&aggregation)
+        || aggregation
+            .strip_prefix("p(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|percentile| percentile.trim().parse::<f64>().ok())
+            .is_some_and(|percentile| (0.0..=100.0).contains(&percentile));
+
+    aggregation_valid && threshold.parse::<f64>().is_ok()
+}
+
+/// Reports a YAML syntax error at the position the parser stopped.
+pub fn parse_diagnostic(error: &ScanError) -> Diagnostic {
+    let position = Position::from(*error.marker());
+    Diagnostic {
+        severity: Severity::Error,
+        message: error.info().to_string(),
+        path: String::new(),
+        line: position.line,
+        column: position.column,
+    }
+}
+
+/// Validates `content` and returns every problem found, in document order.
+///
+/// `definition_path` is the file the content belongs to, if it has been
+/// saved; without it relative `code_file` paths are looked up in the
+/// workspace roots.
+pub fn validate(
+    workspace: &Workspace,
+    content: &str,
+    definition_path: Option<&Path>,
+) -> Vec<Diagnostic> {
+    let root = match yaml_tree::parse(content) {
+        Ok(Some(root)) => root,
+        Ok(None) => return Vec::new(),
+        Err(e) => return vec![parse_diagnostic(&e)],
+    };
+
+    let mut validator = Validator {
+        workspace,
+        base_dir: definition_path.and_then(Path::parent),
+        agents: HashSet::new(),
+        diagnostics: Vec::new(),
+    };
+    validator.check_definition(&root);
+
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Validates a test definition. `content` is the editor buffer; when it is
+/// omitted the file at `path` is read instead.
+#[tauri::command]
+pub fn validate_test_definition(
+    workspace: tauri::State<'_, Workspace>,
+    path: Option<String>,
+    content: Option<String>,
+) -> Result<Vec<Diagnostic>, WorkspaceError> {
+    let path = path
+        .map(|path| workspace.resolve_existing(&path))
+        .transpose()?;
+    let content = match (content, &path) {
+        (Some(content), _) => content,
+        (None, Some(path)) => fs::read_to_string(path).map_err(|e| WorkspaceError::io(path, e))?,
+        (None, None) => {
+            return Err(WorkspaceError::InvalidPath(
+                "either a path or content is required".to_string(),
+            ))
+        }
+    };
+
+    Ok(validate(&workspace, &content, path.as_deref()))
+}
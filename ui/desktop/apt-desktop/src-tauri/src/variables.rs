@@ -0,0 +1,486 @@
+// `${VAR}` references in test definitions.
+//
+// Definitions take agent endpoints, tokens and service URLs from the
+// environment, expanded by the Python loader when a run starts. This resolves
+// them ahead of time against the same sources, so the editor can show what
+// each reference turns into and flag the ones that are not set.
+// `${secret:<name>}` references point into the vault instead.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::profiles::{ProfileError, ProfileStore};
+use crate::validation::{self, Diagnostic, Severity};
+use crate::vault::{self, Vault, VaultError, SECRET_PREFIX};
+use crate::workspace::{Workspace, WorkspaceError};
+use crate::yaml_tree::{self, Node, NodeKind};
+
+const DOTENV_FILE: &str = ".env";
+/// Words that mark a variable or YAML key as holding a secret, matched
+/// against the `_`/`-` separated parts of the name.
+const SECRET_WORDS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "APIKEY",
+    "AUTH",
+    "CREDENTIAL",
+    "CREDENTIALS",
+    "PRIVATE",
+];
+// Fixed length, so the mask does not give away the length of the value.
+const MASK: &str = "********";
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableSource {
+    Process,
+    Dotenv,
+    Profile,
+    Vault,
+}
+
+struct Variable {
+    value: String,
+    source: VariableSource,
+    secret: bool,
+}
+
+/// Variables a run sees. Later sources override earlier ones: the process
+/// environment, then the workspace `.env` file, then the profile.
+pub struct Environment {
+    variables: HashMap<String, Variable>,
+    pub dotenv: Option<PathBuf>,
+    pub profile: Option<String>,
+}
+
+impl Environment {
+    /// Loads the environment for a test definition, or for a run that is not
+    /// tied to one. `profile` defaults to the selected profile.
+    pub fn load(
+        workspace: &Workspace,
+        profiles: &ProfileStore,
+        definition_path: Option<&Path>,
+        profile: Option<&str>,
+    ) -> Result<Environment, ProfileError> {
+        let mut variables: HashMap<String, Variable> = std::env::vars()
+            .map(|(name, value)| {
+                let variable = Variable {
+                    value,
+                    source: VariableSource::Process,
+                    secret: false,
+                };
+                (name, variable)
+            })
+            .collect();
+
+        let dotenv = definition_path.and_then(|path| find_dotenv(workspace, path));
+        if let Some(path) = &dotenv {
+            let content = fs::read_to_string(path).map_err(|e| WorkspaceError::io(path, e))?;
+            for (name, value) in parse_dotenv(&content) {
+                let variable = Variable {
+                    value,
+                    source: VariableSource::Dotenv,
+                    secret: false,
+                };
+                variables.insert(name, variable);
+            }
+        }
+
+        let profile = profile.map(str::to_string).or_else(|| profiles.selected());
+        if let Some(name) = &profile {
+            for (name, variable) in profiles.get(name)?.variables {
+                let variable = Variable {
+                    value: variable.value,
+                    source: VariableSource::Profile,
+                    secret: variable.secret,
+                };
+                variables.insert(name, variable);
+            }
+        }
+
+        Ok(Environment {
+            variables,
+            dotenv,
+            profile,
+        })
+    }
+
+    /// Variables to set on a child process on top of the environment it
+    /// inherits: those from `.env` and the profile, with secret references
+    /// expanded, and one `secret:<name>` variable per secret `definition`
+    /// refers to. The latter is what `os.path.expandvars` looks up for
+    /// `${secret:<name>}`, so the Python loader resolves those as well.
+    pub fn child_variables(
+        &self,
+        vault: &Vault,
+        definition: Option<&str>,
+    ) -> Result<Vec<(String, String)>, VaultError> {
+        let mut variables = Vec::new();
+        for (name, variable) in &self.variables {
+            if !matches!(variable.source, VariableSource::Process) {
+                variables.push((name.clone(), vault.expand(&variable.value)?));
+            }
+        }
+        for name in definition.map(vault::secret_names).unwrap_or_default() {
+            let value = vault.get(name)?;
+            variables.push((format!("{}{}", SECRET_PREFIX, name), value.to_string()));
+        }
+        Ok(variables)
+    }
+
+    /// Replaces the `${VAR}` references in `value`, for settings such as
+    /// agent endpoints that are resolved by the app rather than by a run.
+    /// `${secret:<name>}` references, also those a variable's value
+    /// contains, are left for the vault. Fails with the name of the first
+    /// variable that is not set.
+    pub fn expand(&self, value: &str) -> Result<String, String> {
+        let mut expanded = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(start) = rest.find("${") {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let reference = rest;
+            let name = &reference[2..end];
+            if is_variable_name(name) {
+                let variable = self.variables.get(name).ok_or_else(|| name.to_string())?;
+                expanded.push_str(&variable.value);
+            } else {
+                expanded.push_str(&reference[..=end]);
+            }
+            rest = &reference[end + 1..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+#[derive(Serialize)]
+pub struct VariableReference {
+    pub name: String,
+    /// Dotted location of the value containing the reference, as in
+    /// validation diagnostics.
+    pub path: String,
+    /// 1-based, of the `$`.
+    pub line: usize,
+    /// 1-based, in characters.
+    pub column: usize,
+    /// `None` when unresolved; masked when `secret` is set.
+    pub value: Option<String>,
+    pub source: Option<VariableSource>,
+    pub secret: bool,
+}
+
+#[derive(Serialize)]
+pub struct VariablePreview {
+    pub profile: Option<String>,
+    pub dotenv: Option<PathBuf>,
+    /// In document order.
+    pub references: Vec<VariableReference>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A `${...}` occurrence in a scalar.
+struct Occurrence {
+    /// Byte offset of the `$`.
+    offset: usize,
+    /// Text between the braces, or `None` if the closing brace is missing.
+    name: Option<String>,
+    path: String,
+    /// Mapping key the scalar is the value of.
+    key: Option<String>,
+}
+
+fn collect_occurrences(
+    content: &str,
+    node: &Node,
+    path: &str,
+    key: Option<&str>,
+    occurrences: &mut Vec<Occurrence>,
+) {
+    match &node.kind {
+        NodeKind::Scalar(_) => {
+            let text = &content[node.start..node.end];
+            let mut from = 0;
+            while let Some(index) = text[from..].find("${") {
+                let start = from + index;
+                let name = text[start + 2..]
+                    .find('}')
+                    .map(|end| text[start + 2..start + 2 + end].to_string());
+                from = start + 2 + name.as_ref().map_or(0, |name| name.len() + 1);
+                occurrences.push(Occurrence {
+                    offset: node.start + start,
+                    name,
+                    path: path.to_string(),
+                    key: key.map(str::to_string),
+                });
+            }
+        }
+        NodeKind::Mapping(entries) => {
+            for (key, value) in entries {
+                let key = key.as_str().unwrap_or_default();
+                let path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                collect_occurrences(content, value, &path, Some(key), occurrences);
+            }
+        }
+        NodeKind::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let path = format!("{}[{}]", path, index);
+                collect_occurrences(content, item, &path, key, occurrences);
+            }
+        }
+        NodeKind::Alias => {}
+    }
+}
+
+/// Checks that a secret is available, without reading it.
+fn secret_problem(vault: &Vault, name: &str) -> Option<(Severity, String)> {
+    if !vault.status().exists {
+        return Some((Severity::Error, VaultError::Missing.to_string()));
+    }
+    match vault.contains(name) {
+        Ok(true) => None,
+        Ok(false) => Some((
+            Severity::Error,
+            format!("secret '{}' is not in the vault", name),
+        )),
+        Err(VaultError::Locked) => Some((
+            Severity::Warning,
+            format!(
+                "the vault is locked; unlock it before running so secret '{}' can be resolved",
+                name
+            ),
+        )),
+        Err(e) => Some((Severity::Error, e.to_string())),
+    }
+}
+
+/// Resolves every `${VAR}` reference in `content`. Comments are skipped, as
+/// are mapping keys.
+pub fn preview(environment: &Environment, vault: &Vault, content: &str) -> VariablePreview {
+    let mut preview = VariablePreview {
+        profile: environment.profile.clone(),
+        dotenv: environment.dotenv.clone(),
+        references: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    let root = match yaml_tree::parse(content) {
+        Ok(Some(root)) => root,
+        Ok(None) => return preview,
+        Err(e) => {
+            preview.diagnostics.push(validation::parse_diagnostic(&e));
+            return preview;
+        }
+    };
+
+    let mut occurrences = Vec::new();
+    collect_occurrences(content, &root, "", None, &mut occurrences);
+
+    for occurrence in occurrences {
+        let (line, column) = line_column(content, occurrence.offset);
+        let diagnostic = |severity, message| Diagnostic {
+            severity,
+            message,
+            path: occurrence.path.clone(),
+            line,
+            column,
+        };
+
+        let name = match occurrence.name {
+            Some(name) if is_variable_name(&name) => name,
+            Some(name)
+                if name
+                    .strip_prefix(SECRET_PREFIX)
+                    .is_some_and(vault::is_secret_name) =>
+            {
+                let secret_name = &name[SECRET_PREFIX.len()..];
+                let problem = secret_problem(vault, secret_name);
+                let available = problem.is_none();
+                if let Some((severity, message)) = problem {
+                    preview.diagnostics.push(diagnostic(severity, message));
+                }
+                preview.references.push(VariableReference {
+                    path: occurrence.path,
+                    line,
+                    column,
+                    value: available.then(|| MASK.to_string()),
+                    source: available.then_some(VariableSource::Vault),
+                    secret: true,
+                    name,
+                });
+                continue;
+            }
+            Some(name) => {
+                preview.diagnostics.push(diagnostic(
+                    Severity::Error,
+                    format!("invalid variable reference '${{{}}}'", name),
+                ));
+                continue;
+            }
+            None => {
+                preview.diagnostics.push(diagnostic(
+                    Severity::Error,
+                    "variable reference is missing its closing '}'".to_string(),
+                ));
+                continue;
+            }
+        };
+
+        let variable = environment.variables.get(&name);
+        let secret = variable.is_some_and(|v| v.secret)
+            || looks_secret(&name)
+            || occurrence.key.as_deref().is_some_and(looks_secret);
+        match variable {
+            None => preview.diagnostics.push(diagnostic(
+                Severity::Error,
+                format!("environment variable '{}' is not set", name),
+            )),
+            Some(variable) if variable.value.is_empty() => preview.diagnostics.push(diagnostic(
+                Severity::Warning,
+                format!("environment variable '{}' is empty", name),
+            )),
+            // Profile and `.env` values may themselves refer to secrets.
+            Some(variable) => {
+                for secret_name in vault::secret_names(&variable.value) {
+                    if let Some((severity, message)) = secret_problem(vault, secret_name) {
+                        preview.diagnostics.push(diagnostic(severity, message));
+                    }
+                }
+            }
+        }
+
+        preview.references.push(VariableReference {
+            path: occurrence.path,
+            line,
+            column,
+            value: variable.map(|v| {
+                if secret && !v.value.is_empty() {
+                    MASK.to_string()
+                } else {
+                    v.value.clone()
+                }
+            }),
+            source: variable.map(|v| v.source),
+            secret,
+            name,
+        });
+    }
+
+    preview.diagnostics.sort_by_key(|d| (d.line, d.column));
+    preview
+}
+
+/// The nearest `.env` file between the definition and its workspace root.
+fn find_dotenv(workspace: &Workspace, definition_path: &Path) -> Option<PathBuf> {
+    let root = workspace.root_of(definition_path)?;
+    definition_path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(&root))
+        .map(|dir| dir.join(DOTENV_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Reads `KEY=value` lines the way docker compose does: `export` prefixes,
+/// `#` comments and quoted values are understood, lines that do not parse
+/// are skipped.
+fn parse_dotenv(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (name, value) = line.split_once('=')?;
+            let name = name.trim();
+            is_variable_name(name).then(|| (name.to_string(), dotenv_value(value.trim())))
+        })
+        .collect()
+}
+
+fn dotenv_value(value: &str) -> String {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut unescaped = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => unescaped.push('\n'),
+                    Some('t') => unescaped.push('\t'),
+                    Some(c) => unescaped.push(c),
+                    None => {}
+                },
+                c => unescaped.push(c),
+            }
+        }
+        unescaped
+    } else if let Some(rest) = value.strip_prefix('\'') {
+        rest.split('\'').next().unwrap_or_default().to_string()
+    } else {
+        // Unquoted values end at an inline comment.
+        value
+            .split(" #")
+            .next()
+            .unwrap_or_default()
+            .trim_end()
+            .to_string()
+    }
+}
+
+pub fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn looks_secret(name: &str) -> bool {
+    name.split(['_', '-'])
+        .any(|word| SECRET_WORDS.contains(&word.to_ascii_uppercase().as_str()))
+}
+
+/// 1-based line and character column of a byte offset.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Lists the `${VAR}` references of a test definition with the values they
+/// resolve to. `profile` defaults to the selected profile; `content` is the
+/// editor buffer, read from `file_path` when omitted.
+#[tauri::command]
+pub fn preview_variables(
+    workspace: tauri::State<'_, Workspace>,
+    profiles: tauri::State<'_, ProfileStore>,
+    vault: tauri::State<'_, Vault>,
+    file_path: String,
+    profile: Option<String>,
+    content: Option<String>,
+) -> Result<VariablePreview, ProfileError> {
+    let file_path = workspace.resolve_existing(&file_path)?;
+    let content = match content {
+        Some(content) => content,
+        None => fs::read_to_string(&file_path).map_err(|e| WorkspaceError::io(&file_path, e))?,
+    };
+
+    let environment =
+        Environment::load(&workspace, &profiles, Some(&file_path), profile.as_deref())?;
+    Ok(preview(&environment, &vault, &content))
+}
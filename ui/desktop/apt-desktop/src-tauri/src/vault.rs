@@ -0,0 +1,487 @@
+// Encrypted store for agent tokens and other secrets.
+//
+// Secrets live in `vault.json` under the app data directory, encrypted as a
+// single XChaCha20-Poly1305 blob with a key derived from the user's
+// passphrase by Argon2id. Definitions and profiles refer to them as
+// `${secret:<name>}`; values are only filled in when a run is started or an
+// agent request is sent, and only while the vault is unlocked.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+use crate::versions;
+use crate::workspace::WorkspaceError;
+
+const VAULT_FILE: &str = "vault.json";
+const FORMAT_VERSION: u32 = 1;
+/// Binds the ciphertext to this file format.
+const ASSOCIATED_DATA: &[u8] = b"apt-desktop vault v1";
+pub const SECRET_PREFIX: &str = "secret:";
+const MIN_PASSPHRASE_LEN: usize = 8;
+// OWASP's baseline for Argon2id: 19 MiB, two passes, one lane.
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+const SALT_LEN: usize = 16;
+/// Same size as `secrets.token_urlsafe(32)`, which `aptcli agent create` uses.
+const GENERATED_TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VaultError {
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error("no vault has been created yet")]
+    Missing,
+    #[error("a vault already exists")]
+    AlreadyExists,
+    #[error("the vault is locked")]
+    Locked,
+    #[error("wrong passphrase")]
+    WrongPassphrase,
+    #[error("the passphrase must be at least {MIN_PASSPHRASE_LEN} characters long")]
+    WeakPassphrase,
+    #[error("secret '{0}' does not exist")]
+    NotFound(String),
+    #[error("invalid secret name '{0}'")]
+    InvalidName(String),
+    #[error("the vault file is damaged: {0}")]
+    Corrupt(String),
+}
+
+impl VaultError {
+    fn kind(&self) -> &'static str {
+        match self {
+            VaultError::Workspace(_) => "workspace",
+            VaultError::Missing => "missing",
+            VaultError::AlreadyExists => "already_exists",
+            VaultError::Locked => "locked",
+            VaultError::WrongPassphrase => "wrong_passphrase",
+            VaultError::WeakPassphrase => "weak_passphrase",
+            VaultError::NotFound(_) => "secret_not_found",
+            VaultError::InvalidName(_) => "invalid_name",
+            VaultError::Corrupt(_) => "corrupt",
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// secret name.
+impl Serialize for VaultError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        let path = match self {
+            VaultError::Workspace(e) => return e.serialize(serializer),
+            VaultError::NotFound(name) | VaultError::InvalidName(name) => Some(name.as_str()),
+            _ => None,
+        };
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    /// Base64.
+    salt: String,
+}
+
+/// On-disk format. Only the KDF parameters are readable without the
+/// passphrase; secret names are encrypted along with the values.
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    /// Base64.
+    nonce: String,
+    /// Base64.
+    ciphertext: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredSecret {
+    value: Zeroizing<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct SecretInfo {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+}
+
+#[derive(Clone)]
+struct Unlocked {
+    key: Zeroizing<[u8; 32]>,
+    kdf: KdfParams,
+    secrets: IndexMap<String, StoredSecret>,
+}
+
+pub struct Vault {
+    path: PathBuf,
+    unlocked: Mutex<Option<Unlocked>>,
+}
+
+impl Vault {
+    pub fn new(data_dir: &Path) -> Vault {
+        Vault {
+            path: data_dir.join(VAULT_FILE),
+            unlocked: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> VaultStatus {
+        VaultStatus {
+            exists: self.path.exists(),
+            unlocked: self.unlocked.lock().unwrap().is_some(),
+        }
+    }
+
+    /// Creates an empty vault and leaves it unlocked.
+    pub fn create(&self, passphrase: &str) -> Result<(), VaultError> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(VaultError::WeakPassphrase);
+        }
+        let mut unlocked = self.unlocked.lock().unwrap();
+        if self.path.exists() {
+            return Err(VaultError::AlreadyExists);
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let kdf = KdfParams {
+            memory_kib: ARGON2_MEMORY_KIB,
+            iterations: ARGON2_ITERATIONS,
+            parallelism: ARGON2_PARALLELISM,
+            salt: STANDARD.encode(salt),
+        };
+        let state = Unlocked {
+            key: derive_key(passphrase, &kdf)?,
+            kdf,
+            secrets: IndexMap::new(),
+        };
+        self.save(&state)?;
+        *unlocked = Some(state);
+        Ok(())
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<(), VaultError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(VaultError::Missing),
+            Err(e) => return Err(WorkspaceError::io(&self.path, e).into()),
+        };
+        let file: VaultFile =
+            serde_json::from_str(&content).map_err(|e| VaultError::Corrupt(e.to_string()))?;
+        if file.version != FORMAT_VERSION {
+            return Err(VaultError::Corrupt(format!(
+                "unsupported version {}",
+                file.version
+            )));
+        }
+
+        let key = derive_key(passphrase, &file.kdf)?;
+        let nonce = decode(&file.nonce)?;
+        if nonce.len() != 24 {
+            return Err(VaultError::Corrupt("invalid nonce".to_string()));
+        }
+        let plaintext = Zeroizing::new(
+            XChaCha20Poly1305::new(key.as_ref().into())
+                .decrypt(
+                    XNonce::from_slice(&nonce),
+                    Payload {
+                        msg: &decode(&file.ciphertext)?,
+                        aad: ASSOCIATED_DATA,
+                    },
+                )
+                // The tag does not tell a wrong key from tampering; a wrong
+                // passphrase is by far the likelier cause.
+                .map_err(|_| VaultError::WrongPassphrase)?,
+        );
+        let secrets =
+            serde_json::from_slice(&plaintext).map_err(|e| VaultError::Corrupt(e.to_string()))?;
+
+        *self.unlocked.lock().unwrap() = Some(Unlocked {
+            key,
+            kdf: file.kdf,
+            secrets,
+        });
+        Ok(())
+    }
+
+    /// Forgets the key and the decrypted secrets.
+    pub fn lock(&self) {
+        *self.unlocked.lock().unwrap() = None;
+    }
+
+    pub fn list(&self) -> Result<Vec<SecretInfo>, VaultError> {
+        self.with_unlocked(|state| {
+            Ok(state
+                .secrets
+                .iter()
+                .map(|(name, secret)| SecretInfo {
+                    name: name.clone(),
+                    created_at: secret.created_at,
+                    updated_at: secret.updated_at,
+                })
+                .collect())
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> Result<bool, VaultError> {
+        self.with_unlocked(|state| Ok(state.secrets.contains_key(name)))
+    }
+
+    pub fn get(&self, name: &str) -> Result<Zeroizing<String>, VaultError> {
+        self.with_unlocked(|state| {
+            state
+                .secrets
+                .get(name)
+                .map(|secret| secret.value.clone())
+                .ok_or_else(|| VaultError::NotFound(name.to_string()))
+        })
+    }
+
+    /// Adds a secret or replaces its value.
+    pub fn store(&self, name: &str, value: Zeroizing<String>) -> Result<(), VaultError> {
+        if !is_secret_name(name) {
+            return Err(VaultError::InvalidName(name.to_string()));
+        }
+        self.update(|state| {
+            let now = Utc::now();
+            let created_at = state.secrets.get(name).map_or(now, |s| s.created_at);
+            state.secrets.insert(
+                name.to_string(),
+                StoredSecret {
+                    value,
+                    created_at,
+                    updated_at: now,
+                },
+            );
+            Ok(())
+        })
+    }
+
+    /// Replaces a secret with a freshly generated token, creating it if
+    /// needed, and returns the new token.
+    pub fn rotate(&self, name: &str) -> Result<Zeroizing<String>, VaultError> {
+        let mut bytes = Zeroizing::new([0u8; GENERATED_TOKEN_BYTES]);
+        OsRng.fill_bytes(bytes.as_mut());
+        let token = Zeroizing::new(URL_SAFE_NO_PAD.encode(bytes.as_ref()));
+        self.store(name, token.clone())?;
+        Ok(token)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), VaultError> {
+        self.update(|state| {
+            state
+                .secrets
+                .shift_remove(name)
+                .map(|_| ())
+                .ok_or_else(|| VaultError::NotFound(name.to_string()))
+        })
+    }
+
+    /// Replaces every `${secret:<name>}` in `value`. Fails if the vault is
+    /// locked or a secret is missing; values without references are returned
+    /// as they are, locked or not.
+    pub fn expand(&self, value: &str) -> Result<String, VaultError> {
+        let mut expanded = value.to_string();
+        for name in secret_names(value) {
+            let reference = format!("${{{}{}}}", SECRET_PREFIX, name);
+            expanded = expanded.replace(&reference, &self.get(name)?);
+        }
+        Ok(expanded)
+    }
+
+    fn with_unlocked<T>(
+        &self,
+        f: impl FnOnce(&Unlocked) -> Result<T, VaultError>,
+    ) -> Result<T, VaultError> {
+        match self.unlocked.lock().unwrap().as_ref() {
+            Some(state) => f(state),
+            None => Err(VaultError::Locked),
+        }
+    }
+
+    /// Applies `change` and re-encrypts the vault; on failure the file and
+    /// the unlocked state are left as they were.
+    fn update(
+        &self,
+        change: impl FnOnce(&mut Unlocked) -> Result<(), VaultError>,
+    ) -> Result<(), VaultError> {
+        let mut unlocked = self.unlocked.lock().unwrap();
+        let state = unlocked.as_mut().ok_or(VaultError::Locked)?;
+        let mut updated = state.clone();
+        change(&mut updated)?;
+        self.save(&updated)?;
+        *state = updated;
+        Ok(())
+    }
+
+    fn save(&self, state: &Unlocked) -> Result<(), VaultError> {
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(&state.secrets).map_err(|e| VaultError::Corrupt(e.to_string()))?,
+        );
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(state.key.as_ref().into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: ASSOCIATED_DATA,
+                },
+            )
+            .map_err(|e| VaultError::Corrupt(e.to_string()))?;
+
+        let file = VaultFile {
+            version: FORMAT_VERSION,
+            kdf: state.kdf.clone(),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        let content =
+            serde_json::to_string_pretty(&file).map_err(|e| VaultError::Corrupt(e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| WorkspaceError::io(dir, e))?;
+        }
+        versions::atomic_write(&self.path, content.as_bytes())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))
+                .map_err(|e| WorkspaceError::io(&self.path, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<Zeroizing<[u8; 32]>, VaultError> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| VaultError::Corrupt(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &decode(&kdf.salt)?, key.as_mut())
+        .map_err(|e| VaultError::Corrupt(e.to_string()))?;
+    Ok(key)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, VaultError> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| VaultError::Corrupt(e.to_string()))
+}
+
+/// Names of the `${secret:<name>}` references in `value`.
+pub fn secret_names(value: &str) -> Vec<&str> {
+    value
+        .match_indices("${secret:")
+        .filter_map(|(start, reference)| {
+            let rest = &value[start + reference.len()..];
+            rest.find('}').map(|end| &rest[..end])
+        })
+        .collect()
+}
+
+/// Names may use letters, digits, `-`, `_` and `.`, as in
+/// `load-agent-token`.
+pub fn is_secret_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[tauri::command]
+pub fn get_vault_status(vault: tauri::State<'_, Vault>) -> VaultStatus {
+    vault.status()
+}
+
+// Key derivation takes a noticeable fraction of a second, so the commands
+// that run it are async and stay off the main thread.
+
+#[tauri::command]
+pub async fn create_vault(
+    vault: tauri::State<'_, Vault>,
+    passphrase: String,
+) -> Result<(), VaultError> {
+    let passphrase = Zeroizing::new(passphrase);
+    vault.create(&passphrase)
+}
+
+#[tauri::command]
+pub async fn unlock_vault(
+    vault: tauri::State<'_, Vault>,
+    passphrase: String,
+) -> Result<(), VaultError> {
+    let passphrase = Zeroizing::new(passphrase);
+    vault.unlock(&passphrase)
+}
+
+#[tauri::command]
+pub fn lock_vault(vault: tauri::State<'_, Vault>) {
+    vault.lock()
+}
+
+#[tauri::command]
+pub fn list_secrets(vault: tauri::State<'_, Vault>) -> Result<Vec<SecretInfo>, VaultError> {
+    vault.list()
+}
+
+#[tauri::command]
+pub fn get_secret(vault: tauri::State<'_, Vault>, name: String) -> Result<String, VaultError> {
+    vault.get(&name).map(|value| value.to_string())
+}
+
+#[tauri::command]
+pub fn store_secret(
+    vault: tauri::State<'_, Vault>,
+    name: String,
+    value: String,
+) -> Result<(), VaultError> {
+    vault.store(&name, Zeroizing::new(value))
+}
+
+/// Generates a new token for `name` and returns it, so it can be handed to
+/// the agent it authenticates against.
+#[tauri::command]
+pub fn rotate_secret(vault: tauri::State<'_, Vault>, name: String) -> Result<String, VaultError> {
+    vault.rotate(&name).map(|token| token.to_string())
+}
+
+#[tauri::command]
+pub fn delete_secret(vault: tauri::State<'_, Vault>, name: String) -> Result<(), VaultError> {
+    vault.delete(&name)
+}
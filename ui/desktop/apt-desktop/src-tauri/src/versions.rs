@@ -0,0 +1,288 @@
+// Atomic saves and version history for files written by the app.
+//
+// A save goes to a temp file next to the target, is fsynced and then renamed
+// over it, so a crash leaves either the old or the new content on disk. The
+// content being replaced is first copied to `.apt-history/<key>/` under the
+// app data directory, which keeps the newest versions of each file for
+// listing, diffing and restoring.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::Serialize;
+use similar::TextDiff;
+use uuid::Uuid;
+
+use crate::workspace::{Workspace, WorkspaceError};
+
+const HISTORY_DIR: &str = ".apt-history";
+/// Records which file a history directory belongs to.
+const SOURCE_FILE: &str = "source";
+const VERSION_EXTENSION: &str = "bak";
+const TEMP_EXTENSION: &str = "apt-tmp";
+const MAX_VERSIONS: usize = 20;
+// Fixed width, so version ids sort chronologically.
+const VERSION_ID_FORMAT: &str = "%Y%m%dT%H%M%S%.6fZ";
+
+#[derive(Serialize)]
+pub struct FileVersion {
+    pub id: String,
+    pub saved_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+pub struct FileVersions {
+    dir: PathBuf,
+    // Saves are serialized so concurrent writes cannot interleave backups
+    // and pruning.
+    lock: Mutex<()>,
+}
+
+impl FileVersions {
+    pub fn new(data_dir: &Path) -> FileVersions {
+        FileVersions {
+            dir: data_dir.join(HISTORY_DIR),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Atomically replaces `path` with `content`. The previous content, if
+    /// any and different, is kept as a version.
+    pub fn save(&self, path: &Path, content: &[u8]) -> Result<(), WorkspaceError> {
+        let _guard = self.lock.lock().unwrap();
+        match fs::read(path) {
+            Ok(previous) if previous == content => return Ok(()),
+            Ok(previous) => self.backup(path, &previous)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(WorkspaceError::io(path, e)),
+        }
+        atomic_write(path, content)
+    }
+
+    /// Versions of `path`, newest first.
+    pub fn list(&self, path: &Path) -> Result<Vec<FileVersion>, WorkspaceError> {
+        let dir = self.file_dir(path);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(WorkspaceError::io(&dir, e)),
+        };
+
+        let mut versions = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| WorkspaceError::io(&dir, e))?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(VERSION_EXTENSION) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(saved_at) = parse_version_id(id) else {
+                continue;
+            };
+            versions.push(FileVersion {
+                id: id.to_string(),
+                saved_at,
+                size: entry.metadata().map(|m| m.len()).unwrap_or_default(),
+            });
+        }
+
+        versions.sort_by(|a, b| b.id.cmp(&a.id));
+        Ok(versions)
+    }
+
+    pub fn read(&self, path: &Path, version_id: &str) -> Result<Vec<u8>, WorkspaceError> {
+        // Also keeps ids such as `../x` from reaching the filesystem.
+        if parse_version_id(version_id).is_none() {
+            return Err(WorkspaceError::InvalidPath(format!(
+                "invalid version id '{}'",
+                version_id
+            )));
+        }
+        let version_path = self.version_path(path, version_id);
+        fs::read(&version_path).map_err(|e| WorkspaceError::io(&version_path, e))
+    }
+
+    /// Unified diff from a version to `against`, another version, or to the
+    /// current content of the file when `against` is `None`.
+    pub fn diff(
+        &self,
+        path: &Path,
+        version_id: &str,
+        against: Option<&str>,
+    ) -> Result<String, WorkspaceError> {
+        let old = self.read(path, version_id)?;
+        let new = match against {
+            Some(id) => self.read(path, id)?,
+            None => match fs::read(path) {
+                Ok(content) => content,
+                // Diffing against a deleted file shows everything removed.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(WorkspaceError::io(path, e)),
+            },
+        };
+
+        let name = path
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().to_string());
+        let old_header = format!("{}@{}", name, version_id);
+        let new_header = match against {
+            Some(id) => format!("{}@{}", name, id),
+            None => name,
+        };
+
+        let old = String::from_utf8_lossy(&old);
+        let new = String::from_utf8_lossy(&new);
+        Ok(TextDiff::from_lines(old.as_ref(), new.as_ref())
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &new_header)
+            .to_string())
+    }
+
+    /// Writes a version back to `path`. The content it replaces becomes a
+    /// version itself, so a restore can be undone like any other save.
+    pub fn restore(&self, path: &Path, version_id: &str) -> Result<Vec<u8>, WorkspaceError> {
+        let content = self.read(path, version_id)?;
+        self.save(path, &content)?;
+        Ok(content)
+    }
+
+    fn backup(&self, path: &Path, content: &[u8]) -> Result<(), WorkspaceError> {
+        let dir = self.file_dir(path);
+        fs::create_dir_all(&dir).map_err(|e| WorkspaceError::io(&dir, e))?;
+        let source = dir.join(SOURCE_FILE);
+        if !source.exists() {
+            fs::write(&source, path.to_string_lossy().as_bytes())
+                .map_err(|e| WorkspaceError::io(&source, e))?;
+        }
+
+        let mut saved_at = Utc::now();
+        let mut version_path = self.version_path(path, &version_id(saved_at));
+        while version_path.exists() {
+            saved_at += Duration::microseconds(1);
+            version_path = self.version_path(path, &version_id(saved_at));
+        }
+        atomic_write(&version_path, content)?;
+
+        for old in self.list(path)?.into_iter().skip(MAX_VERSIONS) {
+            let old_path = self.version_path(path, &old.id);
+            fs::remove_file(&old_path).map_err(|e| WorkspaceError::io(&old_path, e))?;
+        }
+        Ok(())
+    }
+
+    /// History directory of a file, named after a hash of its path.
+    fn file_dir(&self, path: &Path) -> PathBuf {
+        let key = Uuid::new_v5(&Uuid::NAMESPACE_URL, path.to_string_lossy().as_bytes());
+        self.dir.join(key.simple().to_string())
+    }
+
+    fn version_path(&self, path: &Path, version_id: &str) -> PathBuf {
+        self.file_dir(path)
+            .join(format!("{}.{}", version_id, VERSION_EXTENSION))
+    }
+}
+
+fn version_id(saved_at: DateTime<Utc>) -> String {
+    saved_at.format(VERSION_ID_FORMAT).to_string()
+}
+
+fn parse_version_id(id: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(id, VERSION_ID_FORMAT)
+        .ok()
+        .map(|saved_at| saved_at.and_utc())
+}
+
+/// Whether `path` is a temp file of an in-progress atomic write.
+pub fn is_temp_file(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(TEMP_EXTENSION)
+}
+
+/// Writes `content` to a temp file in the same directory, fsyncs it and
+/// renames it over `path`.
+pub fn atomic_write(path: &Path, content: &[u8]) -> Result<(), WorkspaceError> {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(WorkspaceError::InvalidPath(format!(
+            "{} does not name a file",
+            path.display()
+        )));
+    };
+    let temp = dir.join(format!(
+        ".{}.{}.{}",
+        file_name.to_string_lossy(),
+        Uuid::new_v4().simple(),
+        TEMP_EXTENSION
+    ));
+
+    if let Err(e) = write_temp(&temp, path, content).and_then(|()| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(WorkspaceError::io(path, e));
+    }
+    // Persist the rename itself.
+    sync_dir(dir).map_err(|e| WorkspaceError::io(dir, e))
+}
+
+fn write_temp(temp: &Path, target: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(temp)?;
+    file.write_all(content)?;
+    // Keep the permissions of the file being replaced.
+    if let Ok(metadata) = fs::metadata(target) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+// Directories cannot be opened for syncing on Windows; the rename is durable
+// once `MoveFileEx` returns.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// Files are resolved like writes, so versions of a deleted file can still be
+// listed and restored.
+
+#[tauri::command]
+pub fn list_file_versions(
+    workspace: tauri::State<'_, Workspace>,
+    versions: tauri::State<'_, FileVersions>,
+    file_path: String,
+) -> Result<Vec<FileVersion>, WorkspaceError> {
+    let file_path = workspace.resolve_for_write(&file_path)?;
+    versions.list(&file_path)
+}
+
+#[tauri::command]
+pub fn diff_file_version(
+    workspace: tauri::State<'_, Workspace>,
+    versions: tauri::State<'_, FileVersions>,
+    file_path: String,
+    version_id: String,
+    against: Option<String>,
+) -> Result<String, WorkspaceError> {
+    let file_path = workspace.resolve_for_write(&file_path)?;
+    versions.diff(&file_path, &version_id, against.as_deref())
+}
+
+/// Restores a version and returns the restored content.
+#[tauri::command]
+pub fn restore_file_version(
+    workspace: tauri::State<'_, Workspace>,
+    versions: tauri::State<'_, FileVersions>,
+    file_path: String,
+    version_id: String,
+) -> Result<String, WorkspaceError> {
+    let file_path = workspace.resolve_for_write(&file_path)?;
+    let content = versions.restore(&file_path, &version_id)?;
+    Ok(String::from_utf8_lossy(&content).to_string())
+}
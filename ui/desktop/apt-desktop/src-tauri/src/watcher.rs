@@ -0,0 +1,174 @@
+// Filesystem watcher for the workspace roots.
+//
+// Changes to test definitions and result files are debounced and pushed to
+// the frontend as `workspace-changed` events, so pages stay in sync when YAML
+// is edited in another editor or pulled from git.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify_debouncer_full::notify::event::{ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::discovery;
+use crate::versions;
+
+pub const WORKSPACE_CHANGED_EVENT: &str = "workspace-changed";
+
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+const IGNORED_DIRS: &[&str] = &["node_modules", "target", ".git"];
+const RESULTS_DIR: &str = "performance_results";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeCategory {
+    TestDefinition,
+    Result,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct WorkspaceChange {
+    pub kind: ChangeKind,
+    pub category: ChangeCategory,
+    pub path: PathBuf,
+    /// Previous location for `renamed` changes.
+    pub from: Option<PathBuf>,
+}
+
+pub struct WorkspaceWatcher {
+    app: AppHandle,
+    debouncer: Mutex<Option<Debouncer<RecommendedWatcher, FileIdMap>>>,
+}
+
+impl WorkspaceWatcher {
+    pub fn new(app: AppHandle) -> WorkspaceWatcher {
+        WorkspaceWatcher {
+            app,
+            debouncer: Mutex::new(None),
+        }
+    }
+
+    /// Replaces the watched set with `roots`.
+    pub fn watch(&self, roots: &[PathBuf]) -> Result<(), String> {
+        let app = self.app.clone();
+        let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, None, move |result| {
+            let changes = collect_changes(result);
+            if !changes.is_empty() {
+                let _ = app.emit_all(WORKSPACE_CHANGED_EVENT, changes);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+
+        for root in roots {
+            debouncer
+                .watcher()
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(|e| format!("failed to watch {}: {}", root.display(), e))?;
+            // The file id cache lets the debouncer pair up rename events.
+            debouncer.cache().add_root(root, RecursiveMode::Recursive);
+        }
+
+        // Dropping the previous debouncer stops its watcher thread.
+        *self.debouncer.lock().unwrap() = Some(debouncer);
+        Ok(())
+    }
+}
+
+fn collect_changes(result: DebounceEventResult) -> Vec<WorkspaceChange> {
+    let events = match result {
+        Ok(events) => events,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut seen = HashSet::new();
+    let mut changes = Vec::new();
+
+    for event in events {
+        let change = match event.kind {
+            EventKind::Create(_) => single_change(ChangeKind::Created, &event.paths),
+            EventKind::Remove(_) => single_change(ChangeKind::Deleted, &event.paths),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => rename_change(&event.paths),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                single_change(ChangeKind::Deleted, &event.paths)
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                single_change(ChangeKind::Created, &event.paths)
+            }
+            EventKind::Modify(_) => single_change(ChangeKind::Modified, &event.paths),
+            _ => None,
+        };
+
+        if let Some(change) = change {
+            if seen.insert(change.clone()) {
+                changes.push(change);
+            }
+        }
+    }
+
+    changes
+}
+
+fn single_change(kind: ChangeKind, paths: &[PathBuf]) -> Option<WorkspaceChange> {
+    let path = paths.first()?;
+    Some(WorkspaceChange {
+        kind,
+        category: classify(path)?,
+        path: path.clone(),
+        from: None,
+    })
+}
+
+fn rename_change(paths: &[PathBuf]) -> Option<WorkspaceChange> {
+    let [from, to] = paths else {
+        return None;
+    };
+    // Atomic saves write a temp file and rename it over the target.
+    if versions::is_temp_file(from) {
+        return single_change(ChangeKind::Modified, std::slice::from_ref(to));
+    }
+
+    // A definition renamed to a non-YAML name (or moved out of the results
+    // directory) is reported under its old category.
+    let category = classify(to).or_else(|| classify(from))?;
+    Some(WorkspaceChange {
+        kind: ChangeKind::Renamed,
+        category,
+        path: to.clone(),
+        from: Some(from.clone()),
+    })
+}
+
+fn classify(path: &Path) -> Option<ChangeCategory> {
+    let mut in_results = false;
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            let name = name.to_string_lossy();
+            if IGNORED_DIRS.contains(&name.as_ref()) {
+                return None;
+            }
+            in_results |= name == RESULTS_DIR;
+        }
+    }
+
+    if in_results {
+        Some(ChangeCategory::Result)
+    } else if discovery::is_yaml(path) {
+        Some(ChangeCategory::TestDefinition)
+    } else {
+        None
+    }
+}
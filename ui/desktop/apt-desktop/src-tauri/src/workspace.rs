@@ -0,0 +1,355 @@
+// Workspace roots that file commands are confined to.
+//
+// Every path coming from the webview is canonicalized and must resolve to a
+// location under one of the roots, so symlinks and `..` segments cannot be
+// used to reach files elsewhere on disk. The roots are `~/.apt`, any folder
+// the user opened through the native folder picker, and the entries of the
+// `APT_WORKSPACE_ROOTS` environment variable.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::watcher::WorkspaceWatcher;
+
+const CONFIG_FILE: &str = "workspace.json";
+const ROOTS_ENV: &str = "APT_WORKSPACE_ROOTS";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkspaceError {
+    #[error("{} is outside the workspace roots", .0.display())]
+    OutsideWorkspace(PathBuf),
+    #[error("{} does not exist", .0.display())]
+    NotFound(PathBuf),
+    #[error("invalid path: {0}")]
+    InvalidPath(String),
+    #[error("{} is not a workspace root", .0.display())]
+    UnknownRoot(PathBuf),
+    #[error("{}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl WorkspaceError {
+    fn kind(&self) -> &'static str {
+        match self {
+            WorkspaceError::OutsideWorkspace(_) => "outside_workspace",
+            WorkspaceError::NotFound(_) => "not_found",
+            WorkspaceError::InvalidPath(_) => "invalid_path",
+            WorkspaceError::UnknownRoot(_) => "unknown_root",
+            WorkspaceError::Io { .. } => "io",
+        }
+    }
+
+    fn path(&self) -> Option<&Path> {
+        match self {
+            WorkspaceError::OutsideWorkspace(path)
+            | WorkspaceError::NotFound(path)
+            | WorkspaceError::UnknownRoot(path)
+            | WorkspaceError::Io { path, .. } => Some(path),
+            WorkspaceError::InvalidPath(_) => None,
+        }
+    }
+
+    pub fn io(path: &Path, source: io::Error) -> WorkspaceError {
+        if source.kind() == io::ErrorKind::NotFound {
+            WorkspaceError::NotFound(path.to_path_buf())
+        } else {
+            WorkspaceError::Io {
+                path: path.to_path_buf(),
+                source,
+            }
+        }
+    }
+}
+
+// Errors reach the frontend as `{ kind, message, path }` so the UI can tell a
+// sandbox violation apart from an ordinary I/O failure.
+impl Serialize for WorkspaceError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a Path>,
+        }
+
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path: self.path(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct WorkspaceConfig {
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct WorkspaceRoot {
+    pub path: PathBuf,
+    /// Built-in roots come from `~/.apt` or the environment and cannot be
+    /// removed from the app.
+    pub builtin: bool,
+}
+
+pub struct Workspace {
+    builtin_roots: Vec<PathBuf>,
+    user_roots: RwLock<Vec<PathBuf>>,
+    config_path: PathBuf,
+}
+
+impl Workspace {
+    pub fn load(data_dir: &Path) -> Workspace {
+        let mut builtin_roots = Vec::new();
+
+        if let Some(home) = tauri::api::path::home_dir() {
+            let apt_dir = home.join(".apt");
+            let _ = fs::create_dir_all(&apt_dir);
+            builtin_roots.extend(canonical_dir(&apt_dir));
+        }
+        if let Some(paths) = std::env::var_os(ROOTS_ENV) {
+            builtin_roots.extend(std::env::split_paths(&paths).filter_map(|p| canonical_dir(&p)));
+        }
+
+        let config_path = data_dir.join(CONFIG_FILE);
+        let config: WorkspaceConfig = fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Workspace {
+            builtin_roots,
+            user_roots: RwLock::new(
+                config
+                    .roots
+                    .iter()
+                    .filter_map(|p| canonical_dir(p))
+                    .collect(),
+            ),
+            config_path,
+        }
+    }
+
+    pub fn roots(&self) -> Vec<WorkspaceRoot> {
+        let builtin = self.builtin_roots.iter().map(|path| WorkspaceRoot {
+            path: path.clone(),
+            builtin: true,
+        });
+        let user = self.user_roots.read().unwrap().clone();
+        builtin
+            .chain(user.into_iter().map(|path| WorkspaceRoot {
+                path,
+                builtin: false,
+            }))
+            .collect()
+    }
+
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        let mut roots = self.builtin_roots.clone();
+        roots.extend(self.user_roots.read().unwrap().iter().cloned());
+        roots
+    }
+
+    /// The innermost root that contains `path`.
+    pub fn root_of(&self, path: &Path) -> Option<PathBuf> {
+        self.root_paths()
+            .into_iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
+    pub fn add_root(&self, path: &Path) -> Result<PathBuf, WorkspaceError> {
+        let root = path
+            .canonicalize()
+            .map_err(|e| WorkspaceError::io(path, e))?;
+        if !root.is_dir() {
+            return Err(WorkspaceError::InvalidPath(format!(
+                "{} is not a directory",
+                root.display()
+            )));
+        }
+
+        let mut roots = self.user_roots.write().unwrap();
+        if !roots.contains(&root) {
+            roots.push(root.clone());
+            self.save(&roots)?;
+        }
+
+        Ok(root)
+    }
+
+    pub fn remove_root(&self, path: &Path) -> Result<(), WorkspaceError> {
+        if self.builtin_roots.iter().any(|root| root == path) {
+            return Err(WorkspaceError::InvalidPath(format!(
+                "{} is a built-in root and cannot be removed",
+                path.display()
+            )));
+        }
+
+        let mut roots = self.user_roots.write().unwrap();
+        let index = roots
+            .iter()
+            .position(|root| root == path)
+            .ok_or_else(|| WorkspaceError::UnknownRoot(path.to_path_buf()))?;
+        roots.remove(index);
+        self.save(&roots)
+    }
+
+    /// Resolves a path that must already exist.
+    ///
+    /// Relative paths are tried against each root in order, so `examples`
+    /// finds the examples folder of an opened APT checkout.
+    pub fn resolve_existing(&self, path: &str) -> Result<PathBuf, WorkspaceError> {
+        let roots = self.root_paths();
+        let requested = parse_path(path)?;
+
+        let candidates: Vec<PathBuf> = if requested.is_absolute() {
+            vec![requested.clone()]
+        } else {
+            roots.iter().map(|root| root.join(&requested)).collect()
+        };
+
+        for candidate in candidates {
+            match candidate.canonicalize() {
+                Ok(resolved) => return check_inside(&roots, resolved),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(WorkspaceError::io(&candidate, e)),
+            }
+        }
+
+        Err(WorkspaceError::NotFound(requested))
+    }
+
+    /// Resolves a path that is about to be written.
+    ///
+    /// The parent directory must exist inside a workspace root; an existing
+    /// target that is a symlink is followed and checked as well.
+    pub fn resolve_for_write(&self, path: &str) -> Result<PathBuf, WorkspaceError> {
+        let roots = self.root_paths();
+        let requested = parse_path(path)?;
+
+        let file_name = match requested.components().next_back() {
+            Some(Component::Normal(name)) => name.to_os_string(),
+            _ => {
+                return Err(WorkspaceError::InvalidPath(format!(
+                    "{} does not name a file",
+                    requested.display()
+                )))
+            }
+        };
+
+        let parent = requested.parent().unwrap_or_else(|| Path::new(""));
+        let parent = if parent.as_os_str().is_empty() {
+            // A bare file name goes into the first root.
+            roots
+                .first()
+                .cloned()
+                .ok_or_else(|| WorkspaceError::OutsideWorkspace(requested.clone()))?
+        } else {
+            self.resolve_existing(&parent.to_string_lossy())?
+        };
+
+        let target = check_inside(&roots, parent.join(file_name))?;
+        match fs::symlink_metadata(&target) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                let resolved = target
+                    .canonicalize()
+                    .map_err(|e| WorkspaceError::io(&target, e))?;
+                check_inside(&roots, resolved)
+            }
+            _ => Ok(target),
+        }
+    }
+
+    fn save(&self, roots: &[PathBuf]) -> Result<(), WorkspaceError> {
+        let config = WorkspaceConfig {
+            roots: roots.to_vec(),
+        };
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| WorkspaceError::InvalidPath(e.to_string()))?;
+        if let Some(dir) = self.config_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| WorkspaceError::io(dir, e))?;
+        }
+        fs::write(&self.config_path, content).map_err(|e| WorkspaceError::io(&self.config_path, e))
+    }
+}
+
+#[tauri::command]
+pub fn list_workspace_roots(workspace: tauri::State<'_, Workspace>) -> Vec<WorkspaceRoot> {
+    workspace.roots()
+}
+
+/// Lets the user add a root through the native folder picker.
+///
+/// Roots are never accepted as a plain path from the webview; otherwise a
+/// compromised page could simply widen its own sandbox.
+#[tauri::command]
+pub async fn open_workspace_root(
+    workspace: tauri::State<'_, Workspace>,
+    watcher: tauri::State<'_, WorkspaceWatcher>,
+) -> Result<Option<PathBuf>, WorkspaceError> {
+    let picked = tauri::async_runtime::spawn_blocking(|| {
+        tauri::api::dialog::blocking::FileDialogBuilder::new()
+            .set_title("Open APT workspace")
+            .pick_folder()
+    })
+    .await
+    .map_err(|e| WorkspaceError::InvalidPath(e.to_string()))?;
+
+    let root = match picked {
+        Some(path) => workspace.add_root(&path)?,
+        None => return Ok(None),
+    };
+    rewatch(&workspace, &watcher);
+
+    Ok(Some(root))
+}
+
+#[tauri::command]
+pub fn remove_workspace_root(
+    workspace: tauri::State<'_, Workspace>,
+    watcher: tauri::State<'_, WorkspaceWatcher>,
+    path: PathBuf,
+) -> Result<(), WorkspaceError> {
+    workspace.remove_root(&path)?;
+    rewatch(&workspace, &watcher);
+    Ok(())
+}
+
+fn rewatch(workspace: &Workspace, watcher: &WorkspaceWatcher) {
+    // The roots themselves were updated; a watcher failure only costs live
+    // refreshes, so it is not reported as a command error.
+    if let Err(e) = watcher.watch(&workspace.root_paths()) {
+        eprintln!("failed to watch workspace roots: {}", e);
+    }
+}
+
+fn parse_path(path: &str) -> Result<PathBuf, WorkspaceError> {
+    if path.trim().is_empty() {
+        return Err(WorkspaceError::InvalidPath("empty path".to_string()));
+    }
+    if path.contains('\0') {
+        return Err(WorkspaceError::InvalidPath(
+            "path contains a NUL byte".to_string(),
+        ));
+    }
+    Ok(PathBuf::from(path))
+}
+
+fn check_inside(roots: &[PathBuf], path: PathBuf) -> Result<PathBuf, WorkspaceError> {
+    if roots.iter().any(|root| path.starts_with(root)) {
+        Ok(path)
+    } else {
+        Err(WorkspaceError::OutsideWorkspace(path))
+    }
+}
+
+fn canonical_dir(path: &Path) -> Option<PathBuf> {
+    path.canonicalize().ok().filter(|p| p.is_dir())
+}
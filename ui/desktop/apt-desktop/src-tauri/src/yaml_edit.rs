@@ -0,0 +1,851 @@
+// Structured edits applied to YAML text in place.
+//
+// An edit only rewrites the bytes of the node it targets, so comments, key
+// order and formatting everywhere else in the file survive. Inside flow
+// collections (`[...]`, `{...}`) the outermost collection is re-rendered as a
+// whole. After every edit the text is parsed again and compared with the same
+// edit applied to the parsed document; on a mismatch the edit is rejected
+// rather than saved with a different meaning.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_yaml::{Mapping, Value};
+use yaml_rust2::scanner::ScanError;
+
+use crate::versions::FileVersions;
+use crate::workspace::{Workspace, WorkspaceError};
+use crate::yaml_tree::{self, Node, NodeKind, Position};
+
+/// A change to one location in a document. Paths use the same syntax as
+/// validation diagnostics: `workflows.load_test.steps[2].agent`; keys that
+/// contain dots are written as `["key.with.dots"]`.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum YamlEdit {
+    /// Replaces the value at `path`, creating missing mapping keys.
+    Set {
+        path: String,
+        value: serde_json::Value,
+    },
+    /// Appends to the sequence at `path`, creating it when missing.
+    Append {
+        path: String,
+        value: serde_json::Value,
+    },
+    Remove {
+        path: String,
+    },
+}
+
+impl YamlEdit {
+    fn path(&self) -> &str {
+        match self {
+            YamlEdit::Set { path, .. }
+            | YamlEdit::Append { path, .. }
+            | YamlEdit::Remove { path } => path,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EditError {
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+    #[error("invalid YAML at line {line}, column {column}: {message}")]
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("invalid path '{0}'")]
+    InvalidPath(String),
+    /// A key or index on the path is missing. Reported as `missing_path` so
+    /// the frontend can tell it from a missing file (`not_found`).
+    #[error("{0} does not exist")]
+    NotFound(String),
+    #[error("{path} is not a {expected}")]
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+    },
+    #[error("{0} cannot be edited without reformatting the document")]
+    Unsupported(String),
+}
+
+impl EditError {
+    fn kind(&self) -> &'static str {
+        match self {
+            EditError::Workspace(_) => "workspace",
+            EditError::Parse { .. } => "parse",
+            EditError::InvalidPath(_) => "invalid_path",
+            EditError::NotFound(_) => "missing_path",
+            EditError::TypeMismatch { .. } => "type_mismatch",
+            EditError::Unsupported(_) => "unsupported",
+        }
+    }
+
+    fn path(&self) -> Option<&str> {
+        match self {
+            EditError::InvalidPath(path)
+            | EditError::NotFound(path)
+            | EditError::TypeMismatch { path, .. }
+            | EditError::Unsupported(path) => Some(path),
+            EditError::Workspace(_) | EditError::Parse { .. } => None,
+        }
+    }
+}
+
+// Same `{ kind, message, path }` shape as `WorkspaceError`; `path` is the
+// YAML path of the failed edit.
+impl Serialize for EditError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Repr<'a> {
+            kind: &'a str,
+            message: String,
+            path: Option<&'a str>,
+        }
+
+        if let EditError::Workspace(e) = self {
+            return e.serialize(serializer);
+        }
+        Repr {
+            kind: self.kind(),
+            message: self.to_string(),
+            path: self.path(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+enum Op {
+    Set(Value),
+    Append(Value),
+    Remove,
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>, EditError> {
+    let invalid = || EditError::InvalidPath(path.to_string());
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let close = inner.find(']').ok_or_else(invalid)?;
+            let index = &inner[..close];
+            segments.push(
+                match index
+                    .strip_prefix('"')
+                    .and_then(|key| key.strip_suffix('"'))
+                {
+                    Some(key) => Segment::Key(key.to_string()),
+                    None => Segment::Index(index.parse().map_err(|_| invalid())?),
+                },
+            );
+            rest = &inner[close + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(Segment::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+
+        if let Some(next) = rest.strip_prefix('.') {
+            if next.is_empty() || next.starts_with(['.', '[']) {
+                return Err(invalid());
+            }
+            rest = next;
+        }
+    }
+
+    if segments.is_empty() {
+        return Err(invalid());
+    }
+    Ok(segments)
+}
+
+fn value_at<'a>(value: &'a Value, segments: &[Segment]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(value, |value, segment| match segment {
+            Segment::Key(key) => value.as_mapping()?.get(key.as_str()),
+            Segment::Index(index) => value.as_sequence()?.get(*index),
+        })
+}
+
+/// Applies `op` to a parsed document. This defines what an edit means; the
+/// text edit has to produce a document that parses to the same value.
+fn apply_to_value(
+    root: &mut Value,
+    segments: &[Segment],
+    op: &Op,
+    path: &str,
+) -> Result<(), EditError> {
+    let mismatch = |expected| EditError::TypeMismatch {
+        path: path.to_string(),
+        expected,
+    };
+    let not_found = || EditError::NotFound(path.to_string());
+
+    let (last, parents) = segments.split_last().ok_or_else(not_found)?;
+    let create = !matches!(op, Op::Remove);
+
+    let mut current = root;
+    for segment in parents {
+        current = match segment {
+            Segment::Key(key) => {
+                if current.is_null() && create {
+                    *current = Value::Mapping(Mapping::new());
+                }
+                let mapping = current
+                    .as_mapping_mut()
+                    .ok_or_else(|| mismatch("mapping"))?;
+                if create {
+                    mapping
+                        .entry(Value::String(key.clone()))
+                        .or_insert(Value::Null)
+                } else {
+                    mapping.get_mut(key.as_str()).ok_or_else(not_found)?
+                }
+            }
+            Segment::Index(index) => current
+                .as_sequence_mut()
+                .ok_or_else(|| mismatch("sequence"))?
+                .get_mut(*index)
+                .ok_or_else(not_found)?,
+        };
+    }
+
+    match (last, op) {
+        (Segment::Key(key), Op::Set(value)) => {
+            if current.is_null() {
+                *current = Value::Mapping(Mapping::new());
+            }
+            current
+                .as_mapping_mut()
+                .ok_or_else(|| mismatch("mapping"))?
+                .insert(Value::String(key.clone()), value.clone());
+        }
+        (Segment::Index(index), Op::Set(value)) => {
+            *current
+                .as_sequence_mut()
+                .ok_or_else(|| mismatch("sequence"))?
+                .get_mut(*index)
+                .ok_or_else(not_found)? = value.clone();
+        }
+        (_, Op::Append(value)) => {
+            let target = match last {
+                Segment::Key(key) => {
+                    if current.is_null() {
+                        *current = Value::Mapping(Mapping::new());
+                    }
+                    current
+                        .as_mapping_mut()
+                        .ok_or_else(|| mismatch("mapping"))?
+                        .entry(Value::String(key.clone()))
+                        .or_insert(Value::Null)
+                }
+                Segment::Index(index) => current
+                    .as_sequence_mut()
+                    .ok_or_else(|| mismatch("sequence"))?
+                    .get_mut(*index)
+                    .ok_or_else(not_found)?,
+            };
+            if target.is_null() {
+                *target = Value::Sequence(Vec::new());
+            }
+            target
+                .as_sequence_mut()
+                .ok_or_else(|| mismatch("sequence"))?
+                .push(value.clone());
+        }
+        (Segment::Key(key), Op::Remove) => {
+            current
+                .as_mapping_mut()
+                .ok_or_else(|| mismatch("mapping"))?
+                .shift_remove(key.as_str())
+                .ok_or_else(not_found)?;
+        }
+        (Segment::Index(index), Op::Remove) => {
+            let sequence = current
+                .as_sequence_mut()
+                .ok_or_else(|| mismatch("sequence"))?;
+            if *index >= sequence.len() {
+                return Err(not_found());
+            }
+            sequence.remove(*index);
+        }
+    }
+    Ok(())
+}
+
+fn is_block_collection(value: &Value) -> bool {
+    match value {
+        Value::Mapping(mapping) => !mapping.is_empty(),
+        Value::Sequence(sequence) => !sequence.is_empty(),
+        _ => false,
+    }
+}
+
+/// Renders a scalar or an empty collection. Multi-line strings become `|-`
+/// block scalars indented by two spaces.
+fn render_scalar(value: &Value) -> String {
+    serde_yaml::to_string(value)
+        .unwrap_or_default()
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+/// Renders a value in block style, without indentation or a trailing
+/// newline. Nested sequences are indented like the example definitions.
+fn render_block(value: &Value) -> String {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => mapping
+            .iter()
+            .map(|(key, value)| {
+                let key = render_scalar(key);
+                if is_block_collection(value) {
+                    format!(
+                        "{}:\n  {}",
+                        key,
+                        indent_lines(&render_block(value), 2, "\n")
+                    )
+                } else {
+                    format!("{}: {}", key, render_scalar(value))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Sequence(sequence) if !sequence.is_empty() => sequence
+            .iter()
+            .map(|item| format!("- {}", indent_lines(&render_block(item), 2, "\n")))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => render_scalar(value),
+    }
+}
+
+fn render_flow(value: &Value) -> String {
+    match value {
+        Value::Mapping(mapping) => format!(
+            "{{{}}}",
+            mapping
+                .iter()
+                .map(|(key, value)| format!("{}: {}", render_flow(key), render_flow(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Sequence(sequence) => format!(
+            "[{}]",
+            sequence
+                .iter()
+                .map(render_flow)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::String(s) => {
+            let plain = render_scalar(value);
+            // Flow indicators end plain scalars; JSON strings are valid YAML.
+            if plain.contains(['\n', ',', '[', ']', '{', '}']) {
+                serde_json::to_string(s).unwrap_or(plain)
+            } else {
+                plain
+            }
+        }
+        _ => render_scalar(value),
+    }
+}
+
+/// Indents every line after the first by `indent` spaces.
+fn indent_lines(text: &str, indent: usize, newline: &str) -> String {
+    let padding = " ".repeat(indent);
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 || line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{}{}", padding, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(newline)
+}
+
+struct Editor<'a> {
+    content: &'a str,
+    newline: &'static str,
+}
+
+impl Editor<'_> {
+    fn splice(&self, start: usize, end: usize, text: &str) -> String {
+        format!("{}{}{}", &self.content[..start], text, &self.content[end..])
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.content[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Offset of the line break ending the line that contains `pos`.
+    fn line_end(&self, pos: usize) -> usize {
+        let end = self.content[pos..]
+            .find('\n')
+            .map_or(self.content.len(), |i| pos + i);
+        if self.content[..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        }
+    }
+
+    /// Offset just past the line break ending the line that contains `pos`.
+    fn next_line(&self, pos: usize) -> usize {
+        self.content[pos..]
+            .find('\n')
+            .map_or(self.content.len(), |i| pos + i + 1)
+    }
+
+    fn column(&self, pos: usize) -> usize {
+        pos - self.line_start(pos)
+    }
+
+    fn starts_line(&self, pos: usize) -> bool {
+        self.content[self.line_start(pos)..pos].trim().is_empty()
+    }
+
+    fn is_blank_line(&self, start: usize) -> bool {
+        start < self.content.len() && self.content[start..self.line_end(start)].trim().is_empty()
+    }
+
+    fn dash_before(&self, item: &Node) -> usize {
+        self.content[..item.start].rfind('-').unwrap_or(item.start)
+    }
+
+    /// Renders a value for a flow collection, quoting strings the way `like`
+    /// is quoted so a list of `"..."` entries stays consistent.
+    fn render_flow_like(&self, value: &Value, like: &Node) -> String {
+        let quote = match like.kind {
+            NodeKind::Scalar(_) => self.content[like.start..].chars().next(),
+            _ => None,
+        };
+        match (value, quote) {
+            (Value::String(s), Some('"')) => serde_json::to_string(s).unwrap_or_default(),
+            (Value::String(s), Some('\'')) if !s.contains('\n') => {
+                format!("'{}'", s.replace('\'', "''"))
+            }
+            _ => render_flow(value),
+        }
+    }
+
+    fn insert_flow_entry(
+        &self,
+        collection: &Node,
+        key: Option<&str>,
+        value: &Value,
+    ) -> Option<String> {
+        let last = match &collection.kind {
+            NodeKind::Mapping(entries) => entries.last().map(|(_, v)| v),
+            NodeKind::Sequence(items) => items.last(),
+            _ => return None,
+        };
+        let entry = |like: Option<&Node>| {
+            let value = match like {
+                Some(like) => self.render_flow_like(value, like),
+                None => render_flow(value),
+            };
+            match key {
+                Some(key) => format!(
+                    "{}: {}",
+                    render_flow(&Value::String(key.to_string())),
+                    value
+                ),
+                None => value,
+            }
+        };
+
+        match last {
+            Some(last) => {
+                Some(self.splice(last.end, last.end, &format!(", {}", entry(Some(last)))))
+            }
+            None => {
+                let (open, close) = if key.is_some() {
+                    ('{', '}')
+                } else {
+                    ('[', ']')
+                };
+                Some(self.splice(
+                    collection.start,
+                    collection.end,
+                    &format!("{}{}{}", open, entry(None), close),
+                ))
+            }
+        }
+    }
+
+    /// Removes the entry at `index` of a flow collection that has at least
+    /// two entries, together with one separating comma.
+    fn remove_flow_entry(&self, collection: &Node, index: usize) -> Option<String> {
+        let spans: Vec<(usize, usize)> = match &collection.kind {
+            NodeKind::Mapping(entries) => entries.iter().map(|(k, v)| (k.start, v.end)).collect(),
+            NodeKind::Sequence(items) => items.iter().map(|item| (item.start, item.end)).collect(),
+            _ => return None,
+        };
+        let (start, end) = *spans.get(index)?;
+        match (
+            spans.get(index + 1),
+            index.checked_sub(1).and_then(|i| spans.get(i)),
+        ) {
+            (Some((next, _)), _) => Some(self.splice(start, *next, "")),
+            (None, Some((_, previous_end))) => Some(self.splice(*previous_end, end, "")),
+            (None, None) => None,
+        }
+    }
+
+    fn replace_mapping_value(&self, key: &Node, target: &Node, value: &Value) -> String {
+        let colon = yaml_tree::after_colon(self.content, key.end);
+        let indent = self.column(key.start);
+        let same_line = !self.content[colon..target.start.max(colon)].contains('\n');
+
+        if is_block_collection(value) {
+            // Keep a comment on the key line when the old value was below it.
+            let start = if same_line {
+                colon
+            } else {
+                self.line_end(colon)
+            };
+            let text = format!(
+                "{}{}{}",
+                self.newline,
+                " ".repeat(indent + 2),
+                indent_lines(&render_block(value), indent + 2, self.newline)
+            );
+            return self.splice(start, target.end, &text);
+        }
+
+        let scalar = indent_lines(&render_block(value), indent, self.newline);
+        if same_line && target.start > colon {
+            self.splice(target.start, target.end, &scalar)
+        } else {
+            let comment = self.content[colon..self.line_end(colon)].trim();
+            let text = if comment.starts_with('#') {
+                format!(" {}  {}", scalar, comment)
+            } else {
+                format!(" {}", scalar)
+            };
+            self.splice(colon, target.end, &text)
+        }
+    }
+
+    fn replace_item(&self, target: &Node, value: &Value) -> String {
+        let dash = self.dash_before(target);
+        let text = indent_lines(&render_block(value), self.column(dash) + 2, self.newline);
+        if target.start == target.end {
+            self.splice(target.start, target.end, &format!(" {}", text))
+        } else {
+            self.splice(target.start, target.end, &text)
+        }
+    }
+
+    fn insert_entry(&self, mapping: &Node, key: &str, value: &Value) -> Option<String> {
+        let (first_key, _) = mapping.entries().next()?;
+        let indent = self.column(first_key.start);
+        let mut entry = Mapping::new();
+        entry.insert(Value::String(key.to_string()), value.clone());
+
+        // After any comment on the last line of the mapping.
+        let pos = self.line_end(mapping.end);
+        let text = format!(
+            "{}{}{}",
+            self.newline,
+            " ".repeat(indent),
+            indent_lines(&render_block(&Value::Mapping(entry)), indent, self.newline)
+        );
+        Some(self.splice(pos, pos, &text))
+    }
+
+    fn append_item(&self, sequence: &Node, value: &Value) -> Option<String> {
+        let items = sequence.items();
+        let last = items.last()?;
+        let dash = self.dash_before(last);
+        let indent = self.column(dash);
+
+        // Keep blank lines between items if the list already has them.
+        let spaced = items.len() >= 2 && {
+            let previous = &items[items.len() - 2];
+            let mut line = self.next_line(previous.end);
+            let mut blank = false;
+            while line < self.line_start(dash) {
+                blank |= self.is_blank_line(line);
+                line = self.next_line(line);
+            }
+            blank
+        };
+
+        let pos = self.line_end(last.end);
+        let text = format!(
+            "{}{}{}- {}",
+            self.newline,
+            if spaced { self.newline } else { "" },
+            " ".repeat(indent),
+            indent_lines(&render_block(value), indent + 2, self.newline)
+        );
+        Some(self.splice(pos, pos, &text))
+    }
+
+    /// Removes the entry at `index` of a block collection that has at least
+    /// two entries.
+    fn remove_entry(&self, parent: &Node, index: usize) -> Option<String> {
+        let spans: Vec<(usize, usize)> = match &parent.kind {
+            NodeKind::Mapping(entries) => entries.iter().map(|(k, v)| (k.start, v.end)).collect(),
+            NodeKind::Sequence(items) => items
+                .iter()
+                .map(|item| (self.dash_before(item), item.end))
+                .collect(),
+            _ => return None,
+        };
+        let (start, end) = *spans.get(index)?;
+
+        if !self.starts_line(start) {
+            // The first key of a `- key: value` item; the next key moves up.
+            let (next, _) = *spans.get(index + 1)?;
+            return Some(self.splice(start, next, ""));
+        }
+
+        // Comments directly above the entry, at its indentation, go with it.
+        let indent = self.column(start);
+        let mut first_line = self.line_start(start);
+        while first_line > 0 {
+            let previous = self.line_start(first_line - 1);
+            let line = &self.content[previous..self.line_end(previous)];
+            if line.trim_start().starts_with('#') && line.len() - line.trim_start().len() == indent
+            {
+                first_line = previous;
+            } else {
+                break;
+            }
+        }
+
+        let mut last_line_end = self.next_line(end);
+        let blank_before = first_line > 0 && self.is_blank_line(self.line_start(first_line - 1));
+        if blank_before
+            && (last_line_end == self.content.len() || self.is_blank_line(last_line_end))
+        {
+            last_line_end = self.next_line(last_line_end);
+        }
+        Some(self.splice(first_line, last_line_end, ""))
+    }
+}
+
+fn child_count(node: &Node) -> usize {
+    match &node.kind {
+        NodeKind::Mapping(entries) => entries.len(),
+        NodeKind::Sequence(items) => items.len(),
+        _ => 0,
+    }
+}
+
+fn child_index(node: &Node, segment: &Segment) -> Option<usize> {
+    match (&node.kind, segment) {
+        (NodeKind::Mapping(entries), Segment::Key(key)) => entries
+            .iter()
+            .position(|(k, _)| k.as_str() == Some(key.as_str())),
+        (NodeKind::Sequence(items), Segment::Index(index)) => {
+            (*index < items.len()).then_some(*index)
+        }
+        _ => None,
+    }
+}
+
+fn edit_text(
+    editor: &Editor,
+    root: &Node,
+    segments: &[Segment],
+    op: &Op,
+    expected: &Value,
+) -> Option<String> {
+    // Each node along the path with the key it is stored under.
+    let mut chain: Vec<(Option<&Node>, &Node)> = vec![(None, root)];
+    for segment in segments {
+        let (_, node) = chain[chain.len() - 1];
+        let next = match (&node.kind, child_index(node, segment)) {
+            (NodeKind::Mapping(entries), Some(i)) => (Some(&entries[i].0), &entries[i].1),
+            (NodeKind::Sequence(items), Some(i)) => (None, &items[i]),
+            _ => break,
+        };
+        chain.push(next);
+    }
+    let depth = chain.len() - 1;
+    let found = depth == segments.len();
+    let at = |n: usize| value_at(expected, &segments[..n]);
+
+    let replace = |n: usize| -> Option<String> {
+        let (key, target) = chain[n];
+        let value = at(n)?;
+        match (n, key) {
+            (0, _) => Some(editor.splice(target.start, target.end, &render_block(value))),
+            (_, Some(key)) => Some(editor.replace_mapping_value(key, target, value)),
+            (_, None) => Some(editor.replace_item(target, value)),
+        }
+    };
+
+    // The node whose children change.
+    let container = match op {
+        Op::Set(_) | Op::Remove if found => depth - 1,
+        _ => depth,
+    };
+    let (_, parent) = chain[container];
+    if parent.flow {
+        let edited = match op {
+            Op::Set(value) if found => {
+                let (_, target) = chain[depth];
+                let text = editor.render_flow_like(value, target);
+                Some(editor.splice(target.start, target.end, &text))
+            }
+            Op::Remove if child_count(parent) > 1 => {
+                editor.remove_flow_entry(parent, child_index(parent, &segments[depth - 1])?)
+            }
+            Op::Append(value) if found => editor.insert_flow_entry(parent, None, value),
+            _ => match segments.get(depth) {
+                Some(Segment::Key(key)) if matches!(parent.kind, NodeKind::Mapping(_)) => {
+                    editor.insert_flow_entry(parent, Some(key), at(depth + 1)?)
+                }
+                _ => None,
+            },
+        };
+        if edited.is_some() {
+            return edited;
+        }
+    }
+    // Anything else inside a flow collection rewrites the outermost one.
+    if let Some(n) = chain[..=container].iter().position(|(_, node)| node.flow) {
+        let node = chain[n].1;
+        return Some(editor.splice(node.start, node.end, &render_flow(at(n)?)));
+    }
+
+    let (_, node) = chain[depth];
+    match op {
+        Op::Set(_) if found => replace(depth),
+        Op::Remove => {
+            let (_, parent) = chain[depth - 1];
+            if child_count(parent) == 1 {
+                // The parent becomes an empty `{}` or `[]`.
+                replace(depth - 1)
+            } else {
+                editor.remove_entry(parent, child_index(parent, &segments[depth - 1])?)
+            }
+        }
+        Op::Append(value) if found && matches!(node.kind, NodeKind::Sequence(_)) => {
+            editor.append_item(node, value)
+        }
+        _ => match (&node.kind, segments.get(depth)) {
+            (NodeKind::Mapping(_), Some(Segment::Key(key))) => {
+                editor.insert_entry(node, key, at(depth + 1)?)
+            }
+            // A null value that becomes a collection.
+            (NodeKind::Scalar(_), _) => replace(depth),
+            _ => None,
+        },
+    }
+}
+
+fn parse_error(e: ScanError) -> EditError {
+    let position = Position::from(*e.marker());
+    EditError::Parse {
+        line: position.line,
+        column: position.column,
+        message: e.info().to_string(),
+    }
+}
+
+fn apply_edit(content: &str, edit: &YamlEdit) -> Result<String, EditError> {
+    let path = edit.path();
+    let segments = parse_path(path)?;
+    let to_yaml = |value: &serde_json::Value| {
+        serde_yaml::to_value(value).map_err(|e| EditError::InvalidPath(e.to_string()))
+    };
+    let op = match edit {
+        YamlEdit::Set { value, .. } => Op::Set(to_yaml(value)?),
+        YamlEdit::Append { value, .. } => Op::Append(to_yaml(value)?),
+        YamlEdit::Remove { .. } => Op::Remove,
+    };
+
+    let root = yaml_tree::parse(content)
+        .map_err(parse_error)?
+        .filter(|root| !root.is_empty_scalar());
+    let mut expected: Value = match root {
+        Some(_) => serde_yaml::from_str(content).map_err(|e| EditError::Parse {
+            line: e.location().map_or(0, |l| l.line()),
+            column: e.location().map_or(0, |l| l.column()),
+            message: e.to_string(),
+        })?,
+        None => Value::Null,
+    };
+    apply_to_value(&mut expected, &segments, &op, path)?;
+
+    let editor = Editor {
+        content,
+        newline: if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        },
+    };
+    let edited = match &root {
+        Some(root) => edit_text(&editor, root, &segments, &op, &expected),
+        // Empty or comment-only document.
+        None => {
+            let mut edited = content.to_string();
+            if !edited.is_empty() && !edited.ends_with('\n') {
+                edited.push_str(editor.newline);
+            }
+            edited.push_str(&indent_lines(&render_block(&expected), 0, editor.newline));
+            edited.push_str(editor.newline);
+            Some(edited)
+        }
+    };
+
+    let unsupported = || EditError::Unsupported(path.to_string());
+    let edited = edited.ok_or_else(unsupported)?;
+    let actual: Value = serde_yaml::from_str(&edited).map_err(|_| unsupported())?;
+    if actual != expected {
+        return Err(unsupported());
+    }
+    Ok(edited)
+}
+
+/// Applies `edits` in order and returns the new document.
+pub fn apply(content: &str, edits: &[YamlEdit]) -> Result<String, EditError> {
+    edits.iter().try_fold(content.to_string(), |content, edit| {
+        apply_edit(&content, edit)
+    })
+}
+
+/// Applies edits to an editor buffer without touching the disk.
+#[tauri::command]
+pub fn apply_yaml_edits(content: String, edits: Vec<YamlEdit>) -> Result<String, EditError> {
+    apply(&content, &edits)
+}
+
+/// Applies edits to a file in the workspace and returns its new content.
+#[tauri::command]
+pub fn edit_yaml_file(
+    workspace: tauri::State<'_, Workspace>,
+    versions: tauri::State<'_, FileVersions>,
+    file_path: String,
+    edits: Vec<YamlEdit>,
+) -> Result<String, EditError> {
+    let file_path = workspace.resolve_existing(&file_path)?;
+    let content = fs::read_to_string(&file_path).map_err(|e| WorkspaceError::io(&file_path, e))?;
+    let edited = apply(&content, &edits)?;
+    versions.save(&file_path, edited.as_bytes())?;
+    Ok(edited)
+}
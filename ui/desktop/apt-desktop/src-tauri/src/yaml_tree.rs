@@ -0,0 +1,335 @@
+// Position-aware YAML tree.
+//
+// serde_yaml forgets where values came from. The validator needs line and
+// column numbers for its diagnostics and the editor needs byte ranges to
+// splice changes into a file without reformatting the rest of it, so both
+// work on this tree, built from yaml-rust2 parser events. Only the first
+// document of a stream is read.
+
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, ScanError, TScalarStyle};
+
+#[derive(Clone, Copy)]
+pub struct Position {
+    /// 1-based.
+    pub line: usize,
+    /// 1-based, in characters.
+    pub column: usize,
+}
+
+impl From<Marker> for Position {
+    fn from(marker: Marker) -> Position {
+        // yaml-rust2 columns are 0-based.
+        Position {
+            line: marker.line(),
+            column: marker.col() + 1,
+        }
+    }
+}
+
+pub struct Node {
+    pub kind: NodeKind,
+    pub position: Position,
+    /// Byte range of the node in the source. Trailing comments and
+    /// whitespace are not part of it; block scalars include their `|` or `>`
+    /// indicator.
+    pub start: usize,
+    pub end: usize,
+    /// Whether the node is a flow collection (`[...]` or `{...}`).
+    pub flow: bool,
+}
+
+pub enum NodeKind {
+    Scalar(String),
+    Mapping(Vec<(Node, Node)>),
+    Sequence(Vec<Node>),
+    Alias,
+}
+
+impl Node {
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.entries()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&Node, &Node)> {
+        let entries = match &self.kind {
+            NodeKind::Mapping(entries) => entries.as_slice(),
+            _ => &[],
+        };
+        entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn items(&self) -> &[Node] {
+        match &self.kind {
+            NodeKind::Sequence(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Whether the node is a scalar with no text in the source, as in
+    /// `key:` followed by a newline.
+    pub fn is_empty_scalar(&self) -> bool {
+        matches!(self.kind, NodeKind::Scalar(_)) && self.start == self.end
+    }
+}
+
+#[derive(Default)]
+struct Events(Vec<(Event, Marker)>);
+
+impl MarkedEventReceiver for Events {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        self.0.push((event, marker));
+    }
+}
+
+/// Parses `content`, returning `None` for an empty document.
+pub fn parse(content: &str) -> Result<Option<Node>, ScanError> {
+    let mut events = Events::default();
+    Parser::new_from_str(content).load(&mut events, false)?;
+
+    // Markers count characters; the tree works with byte offsets.
+    let mut offsets: Vec<usize> = content.char_indices().map(|(i, _)| i).collect();
+    offsets.push(content.len());
+
+    let mut builder = Builder {
+        content,
+        offsets,
+        events: events.0,
+        index: 0,
+    };
+    while builder.index < builder.events.len() {
+        match builder.events[builder.index].0 {
+            Event::Scalar(..) | Event::SequenceStart(..) | Event::MappingStart(..) => {
+                return Ok(Some(builder.node()))
+            }
+            Event::Alias(_) => return Ok(Some(builder.node())),
+            Event::DocumentEnd | Event::StreamEnd => break,
+            _ => builder.index += 1,
+        }
+    }
+    Ok(None)
+}
+
+struct Builder<'a> {
+    content: &'a str,
+    offsets: Vec<usize>,
+    events: Vec<(Event, Marker)>,
+    index: usize,
+}
+
+impl Builder<'_> {
+    fn byte(&self, marker: &Marker) -> usize {
+        self.offsets[marker.index().min(self.offsets.len() - 1)]
+    }
+
+    /// Start of the event after the current one; the current node cannot
+    /// extend past it.
+    fn bound(&self) -> usize {
+        self.events
+            .get(self.index)
+            .map_or(self.content.len(), |(_, marker)| self.byte(marker))
+    }
+
+    fn node(&mut self) -> Node {
+        let (event, marker) = self.events[self.index].clone();
+        self.index += 1;
+        let start = self.byte(&marker);
+        let position = Position::from(marker);
+
+        match event {
+            Event::Scalar(value, style, ..) => {
+                let bound = self.bound().max(start);
+                let (start, end) = scalar_span(self.content, start, bound, style, &value);
+                Node {
+                    kind: NodeKind::Scalar(value),
+                    position,
+                    start,
+                    end,
+                    flow: false,
+                }
+            }
+            Event::Alias(_) => {
+                let text = &self.content[start..];
+                let len = text
+                    .find(|c: char| c.is_whitespace() || ",[]{}".contains(c))
+                    .unwrap_or(text.len());
+                Node {
+                    kind: NodeKind::Alias,
+                    position,
+                    start,
+                    end: start + len,
+                    flow: false,
+                }
+            }
+            Event::SequenceStart(..) => {
+                let flow = self.content[start..].starts_with('[');
+                let mut items = Vec::new();
+                while !matches!(
+                    self.events.get(self.index),
+                    Some((Event::SequenceEnd, _)) | None
+                ) {
+                    let mut item = self.node();
+                    if item.is_empty_scalar() && !flow {
+                        // `-` with nothing after it.
+                        let dash = self.content[..item.start].rfind('-').unwrap_or(item.start);
+                        item.start = dash + 1;
+                        item.end = dash + 1;
+                    }
+                    items.push(item);
+                }
+                let end = self.collection_end(flow, items.last().map(|item| item.end), start);
+                Node {
+                    kind: NodeKind::Sequence(items),
+                    position,
+                    start,
+                    end,
+                    flow,
+                }
+            }
+            Event::MappingStart(..) => {
+                let flow = self.content[start..].starts_with('{');
+                let mut entries: Vec<(Node, Node)> = Vec::new();
+                while !matches!(
+                    self.events.get(self.index),
+                    Some((Event::MappingEnd, _)) | None
+                ) {
+                    let key = self.node();
+                    let mut value = self.node();
+                    if value.is_empty_scalar() {
+                        // `key:` with nothing after it; yaml-rust2 reports
+                        // the value where the next token starts.
+                        let colon = after_colon(self.content, key.end);
+                        value.start = colon;
+                        value.end = colon;
+                    }
+                    entries.push((key, value));
+                }
+                let end = self.collection_end(flow, entries.last().map(|(_, v)| v.end), start);
+
+                // Block mappings are reported after their first key; point
+                // at the key instead.
+                let (start, position) = match entries.first() {
+                    Some((key, _)) if !flow => (key.start, key.position),
+                    _ => (start, position),
+                };
+                Node {
+                    kind: NodeKind::Mapping(entries),
+                    position,
+                    start,
+                    end,
+                    flow,
+                }
+            }
+            _ => Node {
+                kind: NodeKind::Scalar(String::new()),
+                position,
+                start,
+                end: start,
+                flow: false,
+            },
+        }
+    }
+
+    /// Consumes the end event of a collection and returns the end offset.
+    fn collection_end(&mut self, flow: bool, last_child_end: Option<usize>, start: usize) -> usize {
+        let end_marker = self.events.get(self.index).map(|(_, marker)| *marker);
+        self.index += 1;
+        match (flow, end_marker) {
+            // The end marker points at the closing bracket.
+            (true, Some(marker)) => self.byte(&marker) + 1,
+            _ => last_child_end.unwrap_or(start),
+        }
+    }
+}
+
+/// Returns the offset just past the `:` that follows a mapping key.
+pub fn after_colon(content: &str, key_end: usize) -> usize {
+    content[key_end..]
+        .find(':')
+        .map_or(key_end, |i| key_end + i + 1)
+}
+
+fn scalar_span(
+    content: &str,
+    start: usize,
+    bound: usize,
+    style: TScalarStyle,
+    value: &str,
+) -> (usize, usize) {
+    let text = &content[start..bound];
+    match style {
+        TScalarStyle::SingleQuoted => (start, start + quoted_len(text, '\'')),
+        TScalarStyle::DoubleQuoted => (start, start + quoted_len(text, '"')),
+        TScalarStyle::Literal | TScalarStyle::Folded => {
+            // The marker points into the first content line; the span starts
+            // at the indicator.
+            let indicator = content[..start].rfind(['|', '>']).unwrap_or(start);
+            let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+            (
+                indicator,
+                line_start + block_scalar_len(&content[line_start..bound]),
+            )
+        }
+        TScalarStyle::Plain => (start, start + plain_len(text, value)),
+    }
+}
+
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if quote == '"' && c == '\\' {
+            chars.next();
+        } else if c == quote {
+            // `''` is an escaped quote in single-quoted scalars.
+            if quote == '\'' && matches!(chars.peek(), Some((_, '\''))) {
+                chars.next();
+            } else {
+                return i + c.len_utf8();
+            }
+        }
+    }
+    text.trim_end().len()
+}
+
+/// Plain scalars cannot contain comments, so the source is the parsed value
+/// with line breaks folded into single spaces; match it word by word.
+fn plain_len(text: &str, value: &str) -> usize {
+    let mut end = 0;
+    for word in value.split_whitespace() {
+        match text[end..].find(word) {
+            Some(i) => end += i + word.len(),
+            None => return text.trim_end().len(),
+        }
+    }
+    end
+}
+
+/// Block scalars end at their last content line; anything less indented
+/// after it (comments, the next key) belongs to the surrounding document.
+/// `text` starts at the beginning of the first content line.
+fn block_scalar_len(text: &str) -> usize {
+    let indent = text.len() - text.trim_start_matches(' ').len();
+
+    let mut end = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if !content.trim().is_empty() {
+            if content.len() - content.trim_start().len() < indent {
+                break;
+            }
+            end = offset + content.len();
+        }
+        offset += line.len();
+    }
+    end
+}
@@ -0,0 +1,8 @@
+// Backend commands reject either with a plain string or with a typed error
+// object such as `{ kind, message, path }` for workspace violations.
+export function errorMessage(err: unknown): string {
+    if (typeof err === 'object' && err !== null && 'message' in err) {
+        return String((err as { message: unknown }).message);
+    }
+    return String(err);
+}
@@ -1,5 +1,6 @@
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import {
+    Alert,
     Box,
     Typography,
     Card,
@@ -18,6 +19,8 @@ import {
     DialogContent,
     DialogActions,
     TextField,
+    FormControlLabel,
+    Checkbox,
 } from '@mui/material';
 import {
     Add,
@@ -25,51 +28,261 @@ import {
     Refresh,
     CloudUpload,
     Visibility,
+    FileDownload,
 } from '@mui/icons-material';
 import { invoke } from '@tauri-apps/api/tauri';
+import { save } from '@tauri-apps/api/dialog';
+import { listen } from '@tauri-apps/api/event';
+import { errorMessage } from '../errors';
+
+interface GeneratedFile {
+    path: string;
+    content: string;
+    executable: boolean;
+}
+
+interface SshTarget {
+    host: string;
+    user?: string;
+    port?: number;
+    key_file?: string;
+}
 
 interface Agent {
-    id: string;
     name: string;
-    type: string;
-    status: 'healthy' | 'unhealthy' | 'unknown';
+    deployment?: 'docker' | 'cron' | 'systemd' | 'shell';
+    mode: 'emit' | 'serve';
     endpoint?: string;
+    auth_token?: string;
+    ssh?: SshTarget;
+    labels: Record<string, string>;
+    capacity?: number;
+}
+
+interface ImportSummary {
+    added: string[];
+    updated: string[];
+    skipped: string[];
+}
+
+interface TestDefinitionSummary {
+    path: string;
+    relative_path: string;
+    sections: string[];
 }
 
+type AgentStatus = 'healthy' | 'unhealthy' | 'unknown';
+
+interface DeployProgress {
+    agent: string;
+    stage: 'connect' | 'prepare' | 'transfer' | 'install' | 'verify';
+    status: 'started' | 'completed' | 'failed';
+    message: string;
+    at: string;
+}
+
+interface AgentHealthState {
+    agent: string;
+    status: AgentStatus;
+    last_error: string | null;
+    uptime_percentage: number;
+    history: { latency_ms: number | null }[];
+}
+
+// Same `[user@]host[:port]` format as `aptcli agent deploy --target`.
+const parseSshTarget = (target: string): SshTarget | undefined => {
+    const trimmed = target.trim();
+    if (!trimmed) return undefined;
+    const at = trimmed.indexOf('@');
+    const user = at >= 0 ? trimmed.slice(0, at) : undefined;
+    const rest = trimmed.slice(at + 1);
+    const match = rest.match(/^(.*):(\d+)$/);
+    return match
+        ? { host: match[1], user, port: Number(match[2]) }
+        : { host: rest, user };
+};
+
 export default function AgentManager() {
-    const [agents, setAgents] = useState<Agent[]>([
-        { id: '1', name: 'us-east-1', type: 'docker', status: 'healthy', endpoint: 'http://server1.com:9090' },
-        { id: '2', name: 'eu-west-1', type: 'systemd', status: 'unknown', endpoint: 'http://server2.com:9090' },
-    ]);
+    const [agents, setAgents] = useState<Agent[]>([]);
+    const [health, setHealth] = useState<Record<string, AgentHealthState>>({});
+    const [error, setError] = useState('');
     const [createDialogOpen, setCreateDialogOpen] = useState(false);
     const [newAgentName, setNewAgentName] = useState('');
-    const [newAgentType, setNewAgentType] = useState('docker');
+    const [newAgentType, setNewAgentType] = useState<NonNullable<Agent['deployment']>>('docker');
+    const [newAgentMode, setNewAgentMode] = useState<Agent['mode']>('serve');
+    const [newAgentEndpoint, setNewAgentEndpoint] = useState('');
+    const [newAgentToken, setNewAgentToken] = useState('');
+    const [newAgentTarget, setNewAgentTarget] = useState('');
+    const [importDialogOpen, setImportDialogOpen] = useState(false);
+    const [definitions, setDefinitions] = useState<TestDefinitionSummary[]>([]);
+    const [importPath, setImportPath] = useState('');
+    const [importReplace, setImportReplace] = useState(false);
+    const [deployTarget, setDeployTarget] = useState<string | null>(null);
+    const [deployProgress, setDeployProgress] = useState<DeployProgress[]>([]);
+    const [deploying, setDeploying] = useState(false);
+    const [deployError, setDeployError] = useState('');
+    const [packageFiles, setPackageFiles] = useState<GeneratedFile[] | null>(null);
+    const [packageFile, setPackageFile] = useState(0);
 
-    const createAgent = async () => {
+    useEffect(() => {
+        loadAgents();
+
+        const unlisten = listen('agent-status-changed', () => {
+            loadHealth();
+        });
+
+        return () => {
+            unlisten.then((f) => f());
+        };
+    }, []);
+
+    const loadAgents = async () => {
+        try {
+            setAgents(await invoke<Agent[]>('list_agents'));
+        } catch (err) {
+            setError('Failed to load agents: ' + errorMessage(err));
+        }
+        await loadHealth();
+    };
+
+    const loadHealth = async () => {
+        try {
+            const states = await invoke<AgentHealthState[]>('get_agent_health_states');
+            setHealth(Object.fromEntries(states.map((state) => [state.agent, state])));
+        } catch (err) {
+            setError('Failed to load agent health: ' + errorMessage(err));
+        }
+    };
+
+    // Registry changes are picked up by the monitor on its next round;
+    // this checks right away.
+    const refresh = async () => {
+        await invoke('check_agent_health_now');
+        await loadAgents();
+    };
+
+    const statusOf = (agent: Agent): AgentStatus => health[agent.name]?.status ?? 'unknown';
+
+    const latencyOf = (agent: Agent) => {
+        const latency = health[agent.name]?.history.at(-1)?.latency_ms;
+        return latency != null ? `${latency} ms` : '-';
+    };
+
+    // Same package as `aptcli agent create`, with the default server settings.
+    const packageSpec = () => ({
+        name: newAgentName,
+        deployment: newAgentType,
+        mode: newAgentMode,
+        auth_token: newAgentToken.trim() || undefined,
+    });
+
+    const previewPackage = async () => {
+        try {
+            setPackageFiles(await invoke<GeneratedFile[]>('preview_agent_package', { spec: packageSpec() }));
+            setPackageFile(0);
+        } catch (err) {
+            setError('Failed to preview agent package: ' + errorMessage(err));
+        }
+    };
+
+    const exportPackage = async () => {
         try {
-            await invoke('run_aptcli', {
-                args: ['agent', 'create', '--name', newAgentName, '--type', newAgentType, '--mode', 'emit'],
+            const filePath = await save({
+                defaultPath: `${newAgentName}.tar.gz`,
+                filters: [{ name: 'Agent package', extensions: ['gz'] }],
             });
+            if (typeof filePath === 'string') {
+                await invoke('export_agent_package', { spec: packageSpec(), filePath });
+            }
+        } catch (err) {
+            setError('Failed to export agent package: ' + errorMessage(err));
+        }
+    };
 
-            setAgents([
-                ...agents,
-                {
-                    id: Date.now().toString(),
+    const createAgent = async () => {
+        try {
+            await invoke('write_agent_package', { spec: packageSpec() });
+
+            await invoke('create_agent', {
+                agent: {
                     name: newAgentName,
-                    type: newAgentType,
-                    status: 'unknown',
+                    deployment: newAgentType,
+                    mode: newAgentMode,
+                    endpoint: newAgentEndpoint.trim() || undefined,
+                    auth_token: newAgentToken.trim() || undefined,
+                    ssh: parseSshTarget(newAgentTarget),
+                    labels: {},
                 },
-            ]);
+            });
+            await refresh();
 
             setCreateDialogOpen(false);
             setNewAgentName('');
-        } catch (error) {
-            console.error('Failed to create agent:', error);
+            setNewAgentEndpoint('');
+            setNewAgentToken('');
+            setNewAgentTarget('');
+        } catch (err) {
+            setError('Failed to create agent: ' + errorMessage(err));
+        }
+    };
+
+    const deleteAgent = async (name: string) => {
+        try {
+            await invoke('delete_agent', { name });
+            await loadAgents();
+        } catch (err) {
+            setError('Failed to delete agent: ' + errorMessage(err));
+        }
+    };
+
+    const deployAgent = async (name: string) => {
+        setDeployTarget(name);
+        setDeployProgress([]);
+        setDeployError('');
+        setDeploying(true);
+
+        const unlisten = await listen<DeployProgress>('agent-deploy-progress', (event) => {
+            if (event.payload.agent === name) {
+                setDeployProgress((progress) => [...progress, event.payload]);
+            }
+        });
+        try {
+            await invoke('deploy_agent', { name });
+            await refresh();
+        } catch (err) {
+            setDeployError(errorMessage(err));
+        } finally {
+            unlisten();
+            setDeploying(false);
         }
     };
 
-    const deleteAgent = (id: string) => {
-        setAgents(agents.filter((agent) => agent.id !== id));
+    const openImportDialog = async () => {
+        try {
+            const files = await invoke<TestDefinitionSummary[]>('discover_test_definitions');
+            const withAgents = files.filter((file) => file.sections.includes('agents'));
+            setDefinitions(withAgents);
+            setImportPath(withAgents[0]?.path ?? '');
+            setImportDialogOpen(true);
+        } catch (err) {
+            setError('Failed to load test definitions: ' + errorMessage(err));
+        }
+    };
+
+    const importAgents = async () => {
+        try {
+            const summary = await invoke<ImportSummary>('import_agents', {
+                filePath: importPath,
+                replace: importReplace,
+            });
+            await refresh();
+            setImportDialogOpen(false);
+            if (summary.skipped.length > 0) {
+                setError(`Already registered, not imported: ${summary.skipped.join(', ')}`);
+            }
+        } catch (err) {
+            setError('Failed to import agents: ' + errorMessage(err));
+        }
     };
 
     return (
@@ -82,12 +295,17 @@ export default function AgentManager() {
                     <Button
                         variant="outlined"
                         startIcon={<Refresh />}
-                        onClick={() => {
-                            // Refresh agents
-                        }}
+                        onClick={refresh}
                     >
                         Refresh
                     </Button>
+                    <Button
+                        variant="outlined"
+                        startIcon={<FileDownload />}
+                        onClick={openImportDialog}
+                    >
+                        Import
+                    </Button>
                     <Button
                         variant="contained"
                         startIcon={<Add />}
@@ -98,6 +316,12 @@ export default function AgentManager() {
                 </Box>
             </Box>
 
+            {error && (
+                <Alert severity="error" sx={{ mb: 3 }} onClose={() => setError('')}>
+                    {error}
+                </Alert>
+            )}
+
             <Card>
                 <CardContent>
                     <TableContainer>
@@ -107,43 +331,57 @@ export default function AgentManager() {
                                     <TableCell>Name</TableCell>
                                     <TableCell>Type</TableCell>
                                     <TableCell>Status</TableCell>
+                                    <TableCell>Latency</TableCell>
+                                    <TableCell>Uptime</TableCell>
                                     <TableCell>Endpoint</TableCell>
                                     <TableCell align="right">Actions</TableCell>
                                 </TableRow>
                             </TableHead>
                             <TableBody>
                                 {agents.map((agent) => (
-                                    <TableRow key={agent.id}>
+                                    <TableRow key={agent.name}>
                                         <TableCell>{agent.name}</TableCell>
                                         <TableCell>
-                                            <Chip label={agent.type} size="small" />
+                                            <Chip label={agent.deployment ?? 'external'} size="small" />
                                         </TableCell>
                                         <TableCell>
                                             <Chip
-                                                label={agent.status}
+                                                label={statusOf(agent)}
+                                                title={health[agent.name]?.last_error ?? undefined}
                                                 size="small"
                                                 color={
-                                                    agent.status === 'healthy'
+                                                    statusOf(agent) === 'healthy'
                                                         ? 'success'
-                                                        : agent.status === 'unhealthy'
+                                                        : statusOf(agent) === 'unhealthy'
                                                             ? 'error'
                                                             : 'default'
                                                 }
                                             />
                                         </TableCell>
+                                        <TableCell>{latencyOf(agent)}</TableCell>
+                                        <TableCell>
+                                            {health[agent.name]?.history.length
+                                                ? `${health[agent.name].uptime_percentage.toFixed(1)}%`
+                                                : '-'}
+                                        </TableCell>
                                         <TableCell>{agent.endpoint || '-'}</TableCell>
                                         <TableCell align="right">
                                             <IconButton size="small" title="View Logs">
                                                 <Visibility />
                                             </IconButton>
-                                            <IconButton size="small" title="Deploy">
+                                            <IconButton
+                                                size="small"
+                                                title={agent.ssh && agent.deployment ? 'Deploy' : 'Needs an SSH target and a deployment type'}
+                                                disabled={!agent.ssh || !agent.deployment || deploying}
+                                                onClick={() => deployAgent(agent.name)}
+                                            >
                                                 <CloudUpload />
                                             </IconButton>
                                             <IconButton
                                                 size="small"
                                                 color="error"
                                                 title="Delete"
-                                                onClick={() => deleteAgent(agent.id)}
+                                                onClick={() => deleteAgent(agent.name)}
                                             >
                                                 <Delete />
                                             </IconButton>
@@ -170,7 +408,7 @@ export default function AgentManager() {
                             select
                             label="Deployment Type"
                             value={newAgentType}
-                            onChange={(e) => setNewAgentType(e.target.value)}
+                            onChange={(e) => setNewAgentType(e.target.value as NonNullable<Agent['deployment']>)}
                             fullWidth
                             SelectProps={{ native: true }}
                         >
@@ -179,15 +417,157 @@ export default function AgentManager() {
                             <option value="systemd">Systemd</option>
                             <option value="shell">Shell</option>
                         </TextField>
+                        <TextField
+                            select
+                            label="Mode"
+                            value={newAgentMode}
+                            onChange={(e) => setNewAgentMode(e.target.value as Agent['mode'])}
+                            fullWidth
+                            SelectProps={{ native: true }}
+                        >
+                            <option value="serve">Serve (job API)</option>
+                            <option value="emit">Emit (push metrics)</option>
+                        </TextField>
+                        <TextField
+                            label="Endpoint"
+                            placeholder="http://agent-host:9090"
+                            value={newAgentEndpoint}
+                            onChange={(e) => setNewAgentEndpoint(e.target.value)}
+                            fullWidth
+                        />
+                        <TextField
+                            label="Auth Token"
+                            placeholder="${secret:agent-token}"
+                            helperText="A ${secret:name} or ${VAR} reference"
+                            value={newAgentToken}
+                            onChange={(e) => setNewAgentToken(e.target.value)}
+                            fullWidth
+                        />
+                        <TextField
+                            label="SSH Target"
+                            placeholder="user@host:22"
+                            value={newAgentTarget}
+                            onChange={(e) => setNewAgentTarget(e.target.value)}
+                            fullWidth
+                        />
                     </Box>
                 </DialogContent>
                 <DialogActions>
                     <Button onClick={() => setCreateDialogOpen(false)}>Cancel</Button>
+                    <Button onClick={previewPackage} disabled={!newAgentName.trim()}>
+                        Preview Files
+                    </Button>
+                    <Button onClick={exportPackage} disabled={!newAgentName.trim()}>
+                        Export Bundle
+                    </Button>
                     <Button variant="contained" onClick={createAgent}>
                         Create
                     </Button>
                 </DialogActions>
             </Dialog>
+
+            <Dialog open={packageFiles !== null} onClose={() => setPackageFiles(null)} fullWidth maxWidth="md">
+                <DialogTitle>Package Files</DialogTitle>
+                <DialogContent>
+                    <Box display="flex" flexWrap="wrap" gap={1} pt={1} mb={2}>
+                        {packageFiles?.map((file, index) => (
+                            <Chip
+                                key={file.path}
+                                label={file.executable ? `${file.path} (executable)` : file.path}
+                                color={index === packageFile ? 'primary' : 'default'}
+                                onClick={() => setPackageFile(index)}
+                            />
+                        ))}
+                    </Box>
+                    <Box
+                        component="pre"
+                        sx={{
+                            p: 2,
+                            maxHeight: 400,
+                            bgcolor: 'background.default',
+                            borderRadius: 1,
+                            overflow: 'auto',
+                            fontSize: '0.875rem',
+                        }}
+                    >
+                        {packageFiles?.[packageFile]?.content}
+                    </Box>
+                </DialogContent>
+                <DialogActions>
+                    <Button onClick={() => setPackageFiles(null)}>Close</Button>
+                </DialogActions>
+            </Dialog>
+
+            <Dialog open={deployTarget !== null} onClose={() => !deploying && setDeployTarget(null)} fullWidth>
+                <DialogTitle>Deploy {deployTarget}</DialogTitle>
+                <DialogContent>
+                    <Box display="flex" flexDirection="column" gap={1} pt={1}>
+                        {deployProgress.map((progress, index) => (
+                            <Box key={index} display="flex" alignItems="center" gap={1}>
+                                <Chip
+                                    label={progress.stage}
+                                    size="small"
+                                    color={
+                                        progress.status === 'completed'
+                                            ? 'success'
+                                            : progress.status === 'failed'
+                                                ? 'error'
+                                                : 'default'
+                                    }
+                                />
+                                <Typography variant="body2">{progress.message}</Typography>
+                            </Box>
+                        ))}
+                        {deployError && <Alert severity="error">{deployError}</Alert>}
+                        {!deploying && !deployError && deployProgress.length > 0 && (
+                            <Alert severity="success">Agent deployed</Alert>
+                        )}
+                    </Box>
+                </DialogContent>
+                <DialogActions>
+                    <Button onClick={() => setDeployTarget(null)} disabled={deploying}>
+                        Close
+                    </Button>
+                </DialogActions>
+            </Dialog>
+
+            <Dialog open={importDialogOpen} onClose={() => setImportDialogOpen(false)}>
+                <DialogTitle>Import Agents</DialogTitle>
+                <DialogContent>
+                    <Box display="flex" flexDirection="column" gap={2} pt={1}>
+                        <TextField
+                            select
+                            label="Test Definition"
+                            value={importPath}
+                            onChange={(e) => setImportPath(e.target.value)}
+                            helperText={definitions.length === 0 ? 'No test definition has an agents section' : undefined}
+                            fullWidth
+                            SelectProps={{ native: true }}
+                        >
+                            {definitions.map((definition) => (
+                                <option key={definition.path} value={definition.path}>
+                                    {definition.relative_path}
+                                </option>
+                            ))}
+                        </TextField>
+                        <FormControlLabel
+                            control={
+                                <Checkbox
+                                    checked={importReplace}
+                                    onChange={(e) => setImportReplace(e.target.checked)}
+                                />
+                            }
+                            label="Update agents that are already registered"
+                        />
+                    </Box>
+                </DialogContent>
+                <DialogActions>
+                    <Button onClick={() => setImportDialogOpen(false)}>Cancel</Button>
+                    <Button variant="contained" onClick={importAgents} disabled={!importPath}>
+                        Import
+                    </Button>
+                </DialogActions>
+            </Dialog>
         </Box>
     );
 }
@@ -19,14 +19,23 @@ import {
     Assessment,
 } from '@mui/icons-material';
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 
 interface TestFile {
     name: string;
     path: string;
 }
 
+interface RunStatistics {
+    total_runs: number;
+    successful_runs: number;
+    failed_runs: number;
+    tests_passed: number;
+    tests_failed: number;
+}
+
 interface AgentStatus {
-    name: string;
+    agent: string;
     status: 'healthy' | 'unhealthy' | 'unknown';
 }
 
@@ -37,39 +46,63 @@ export default function Dashboard() {
         totalTests: 0,
         passedTests: 0,
         failedTests: 0,
-        activeAgents: 0,
     });
 
     useEffect(() => {
         loadDashboardData();
+
+        const unlisteners = [
+            listen('workspace-changed', () => {
+                loadDashboardData();
+            }),
+            listen('agent-status-changed', () => {
+                loadAgentStatuses();
+            }),
+        ];
+
+        return () => {
+            unlisteners.forEach((p) => p.then((f) => f()));
+        };
     }, []);
 
     const loadDashboardData = async () => {
         try {
             // Load test files
-            const files = await invoke<string[]>('get_test_files', {
-                directory: './examples',
-            });
+            const files = await invoke<{ path: string; relative_path: string; test_suite_name: string | null }[]>(
+                'discover_test_definitions'
+            );
 
             setRecentTests(
-                files.slice(0, 5).map((path) => ({
-                    name: path.split('/').pop() || '',
-                    path,
+                files.slice(0, 5).map((file) => ({
+                    name: file.test_suite_name ?? file.relative_path,
+                    path: file.path,
                 }))
             );
 
-            // Mock stats for now
+            const runStats = await invoke<RunStatistics>('get_run_statistics');
+
             setStats({
                 totalTests: files.length,
-                passedTests: Math.floor(files.length * 0.8),
-                failedTests: Math.floor(files.length * 0.2),
-                activeAgents: 2,
+                passedTests: runStats.tests_passed,
+                failedTests: runStats.tests_failed,
             });
         } catch (error) {
             console.error('Failed to load dashboard data:', error);
         }
+
+        await loadAgentStatuses();
     };
 
+    const loadAgentStatuses = async () => {
+        try {
+            setAgents(await invoke<AgentStatus[]>('get_agent_health_states'));
+        } catch (error) {
+            console.error('Failed to load agent statuses:', error);
+        }
+    };
+
+    const activeAgents = agents.filter((agent) => agent.status === 'healthy').length;
+
     return (
         <Box>
             <Typography variant="h4" gutterBottom fontWeight={700}>
@@ -141,7 +174,7 @@ export default function Dashboard() {
                                         Active Agents
                                     </Typography>
                                     <Typography variant="h4" fontWeight={700}>
-                                        {stats.activeAgents}
+                                        {activeAgents}
                                     </Typography>
                                 </Box>
                                 <Cloud sx={{ fontSize: 48, color: 'secondary.main', opacity: 0.3 }} />
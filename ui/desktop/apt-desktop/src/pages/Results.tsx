@@ -1,3 +1,4 @@
+import { useState, useEffect } from 'react';
 import {
     Box,
     Typography,
@@ -5,7 +6,16 @@ import {
     CardContent,
     Grid,
     Chip,
+    Table,
+    TableBody,
+    TableCell,
+    TableContainer,
+    TableHead,
+    TableRow,
+    TablePagination,
+    IconButton,
 } from '@mui/material';
+import { Delete } from '@mui/icons-material';
 import {
     LineChart,
     Line,
@@ -18,16 +28,92 @@ import {
     Legend,
     ResponsiveContainer,
 } from 'recharts';
+import { invoke } from '@tauri-apps/api/tauri';
 
-const mockData = [
-    { name: 'Test 1', duration: 1200, requests: 500, errors: 5 },
-    { name: 'Test 2', duration: 980, requests: 450, errors: 2 },
-    { name: 'Test 3', duration: 1500, requests: 600, errors: 8 },
-    { name: 'Test 4', duration: 1100, requests: 520, errors: 3 },
-    { name: 'Test 5', duration: 950, requests: 480, errors: 1 },
-];
+interface TestSummary {
+    total: number;
+    passed: number;
+    failed: number;
+    errors: number;
+    skipped: number;
+    duration_secs: number;
+}
+
+interface HistoryEntry {
+    run_id: string;
+    kind: 'pytest' | 'aptcli';
+    test_file: string | null;
+    profile: string | null;
+    arguments: string[];
+    started_at: string;
+    finished_at: string;
+    duration_ms: number;
+    exit_code: number | null;
+    success: boolean;
+    cancelled: boolean;
+    tests: TestSummary | null;
+}
+
+interface HistoryPage {
+    total: number;
+    runs: HistoryEntry[];
+}
+
+interface RunStatistics {
+    total_runs: number;
+    successful_runs: number;
+    failed_runs: number;
+    cancelled_runs: number;
+    average_duration_ms: number;
+}
+
+const runName = (run: HistoryEntry) =>
+    run.test_file ? run.test_file.split('/').pop() : `aptcli ${run.arguments.join(' ')}`;
 
 export default function Results() {
+    const [page, setPage] = useState(0);
+    const [rowsPerPage, setRowsPerPage] = useState(10);
+    const [history, setHistory] = useState<HistoryPage>({ total: 0, runs: [] });
+    const [stats, setStats] = useState<RunStatistics | null>(null);
+
+    useEffect(() => {
+        loadHistory();
+    }, [page, rowsPerPage]);
+
+    const loadHistory = async () => {
+        try {
+            const [runs, runStats] = await Promise.all([
+                invoke<HistoryPage>('query_run_history', {
+                    query: { limit: rowsPerPage, offset: page * rowsPerPage },
+                }),
+                invoke<RunStatistics>('get_run_statistics'),
+            ]);
+            setHistory(runs);
+            setStats(runStats);
+        } catch (error) {
+            console.error('Failed to load run history:', error);
+        }
+    };
+
+    const deleteRun = async (runId: string) => {
+        try {
+            await invoke('delete_run_history', { runIds: [runId] });
+            loadHistory();
+        } catch (error) {
+            console.error('Failed to delete run:', error);
+        }
+    };
+
+    const chartData = [...history.runs].reverse().map((run) => ({
+        name: new Date(run.started_at).toLocaleString(),
+        duration: run.duration_ms / 1000,
+        passed: run.tests?.passed ?? 0,
+        failed: (run.tests?.failed ?? 0) + (run.tests?.errors ?? 0),
+    }));
+
+    const finishedRuns = stats ? stats.successful_runs + stats.failed_runs : 0;
+    const successRate = stats && finishedRuns > 0 ? (stats.successful_runs / finishedRuns) * 100 : 0;
+
     return (
         <Box>
             <Typography variant="h4" gutterBottom fontWeight={700}>
@@ -42,9 +128,9 @@ export default function Results() {
                                 Total Tests Run
                             </Typography>
                             <Typography variant="h3" fontWeight={700}>
-                                24
+                                {stats?.total_runs ?? 0}
                             </Typography>
-                            <Chip label="+12% from last week" color="success" size="small" sx={{ mt: 1 }} />
+                            <Chip label={`${stats?.cancelled_runs ?? 0} cancelled`} size="small" sx={{ mt: 1 }} />
                         </CardContent>
                     </Card>
                 </Grid>
@@ -55,10 +141,10 @@ export default function Results() {
                             <Typography color="text.secondary" gutterBottom>
                                 Success Rate
                             </Typography>
-                            <Typography variant="h3" fontWeight={700} color="success.main">
-                                96.2%
+                            <Typography variant="h3" fontWeight={700} color={successRate >= 90 ? 'success.main' : 'error.main'}>
+                                {successRate.toFixed(1)}%
                             </Typography>
-                            <Chip label="+2.1% improvement" color="success" size="small" sx={{ mt: 1 }} />
+                            <Chip label={`${stats?.failed_runs ?? 0} failed`} color="error" size="small" sx={{ mt: 1 }} />
                         </CardContent>
                     </Card>
                 </Grid>
@@ -67,26 +153,25 @@ export default function Results() {
                     <Card>
                         <CardContent>
                             <Typography color="text.secondary" gutterBottom>
-                                Avg Response Time
+                                Avg Run Duration
                             </Typography>
                             <Typography variant="h3" fontWeight={700}>
-                                1.1s
+                                {((stats?.average_duration_ms ?? 0) / 1000).toFixed(1)}s
                             </Typography>
-                            <Chip label="-150ms faster" color="success" size="small" sx={{ mt: 1 }} />
                         </CardContent>
                     </Card>
                 </Grid>
             </Grid>
 
-            <Grid container spacing={3}>
+            <Grid container spacing={3} sx={{ mb: 3 }}>
                 <Grid item xs={12} md={6}>
                     <Card>
                         <CardContent>
                             <Typography variant="h6" gutterBottom fontWeight={600}>
-                                Response Time Trend
+                                Run Duration Trend
                             </Typography>
                             <ResponsiveContainer width="100%" height={300}>
-                                <LineChart data={mockData}>
+                                <LineChart data={chartData}>
                                     <CartesianGrid strokeDasharray="3 3" />
                                     <XAxis dataKey="name" />
                                     <YAxis />
@@ -103,23 +188,87 @@ export default function Results() {
                     <Card>
                         <CardContent>
                             <Typography variant="h6" gutterBottom fontWeight={600}>
-                                Requests & Errors
+                                Passed & Failed Tests
                             </Typography>
                             <ResponsiveContainer width="100%" height={300}>
-                                <BarChart data={mockData}>
+                                <BarChart data={chartData}>
                                     <CartesianGrid strokeDasharray="3 3" />
                                     <XAxis dataKey="name" />
                                     <YAxis />
                                     <Tooltip />
                                     <Legend />
-                                    <Bar dataKey="requests" fill="#3b82f6" />
-                                    <Bar dataKey="errors" fill="#ef4444" />
+                                    <Bar dataKey="passed" fill="#22c55e" />
+                                    <Bar dataKey="failed" fill="#ef4444" />
                                 </BarChart>
                             </ResponsiveContainer>
                         </CardContent>
                     </Card>
                 </Grid>
             </Grid>
+
+            <Card>
+                <CardContent>
+                    <Typography variant="h6" gutterBottom fontWeight={600}>
+                        Run History
+                    </Typography>
+                    <TableContainer>
+                        <Table size="small">
+                            <TableHead>
+                                <TableRow>
+                                    <TableCell>Test</TableCell>
+                                    <TableCell>Profile</TableCell>
+                                    <TableCell>Started</TableCell>
+                                    <TableCell>Duration</TableCell>
+                                    <TableCell>Tests</TableCell>
+                                    <TableCell>Status</TableCell>
+                                    <TableCell align="right">Actions</TableCell>
+                                </TableRow>
+                            </TableHead>
+                            <TableBody>
+                                {history.runs.map((run) => (
+                                    <TableRow key={run.run_id}>
+                                        <TableCell>{runName(run)}</TableCell>
+                                        <TableCell>{run.profile ?? '-'}</TableCell>
+                                        <TableCell>{new Date(run.started_at).toLocaleString()}</TableCell>
+                                        <TableCell>{(run.duration_ms / 1000).toFixed(1)}s</TableCell>
+                                        <TableCell>
+                                            {run.tests ? `${run.tests.passed}/${run.tests.total} passed` : '-'}
+                                        </TableCell>
+                                        <TableCell>
+                                            <Chip
+                                                label={run.cancelled ? 'cancelled' : run.success ? 'passed' : 'failed'}
+                                                size="small"
+                                                color={run.cancelled ? 'default' : run.success ? 'success' : 'error'}
+                                            />
+                                        </TableCell>
+                                        <TableCell align="right">
+                                            <IconButton
+                                                size="small"
+                                                color="error"
+                                                title="Delete"
+                                                onClick={() => deleteRun(run.run_id)}
+                                            >
+                                                <Delete />
+                                            </IconButton>
+                                        </TableCell>
+                                    </TableRow>
+                                ))}
+                            </TableBody>
+                        </Table>
+                    </TableContainer>
+                    <TablePagination
+                        component="div"
+                        count={history.total}
+                        page={page}
+                        onPageChange={(_, newPage) => setPage(newPage)}
+                        rowsPerPage={rowsPerPage}
+                        onRowsPerPageChange={(e) => {
+                            setRowsPerPage(parseInt(e.target.value, 10));
+                            setPage(0);
+                        }}
+                    />
+                </CardContent>
+            </Card>
         </Box>
     );
 }
@@ -15,6 +15,7 @@ import {
 } from '@mui/material';
 import { Save, PlayArrow } from '@mui/icons-material';
 import { invoke } from '@tauri-apps/api/tauri';
+import { errorMessage } from '../errors';
 
 export default function TestEditor() {
     const [testName, setTestName] = useState('');
@@ -59,18 +60,36 @@ reporting:
         }
 
         try {
-            const yaml = generateYAML();
-            const fileName = `./examples/${testName.toLowerCase().replace(/\s+/g, '_')}.yml`;
-
-            await invoke('write_yaml_file', {
-                filePath: fileName,
-                content: yaml,
-            });
+            const key = testName.toLowerCase().replace(/\s+/g, '_');
+            const fileName = `./examples/${key}.yml`;
+
+            // Update the fields this form owns in an existing file so hand
+            // edits and comments survive; only new files use the template.
+            const options = `k6_tests.${key}.options`;
+            try {
+                await invoke('edit_yaml_file', {
+                    filePath: fileName,
+                    edits: [
+                        { op: 'set', path: 'test_info.test_suite_name', value: testName },
+                        { op: 'set', path: `k6_tests.${key}.scenarios[0].url`, value: url },
+                        { op: 'set', path: `${options}.vus`, value: Number(vus) },
+                        { op: 'set', path: `${options}.duration`, value: duration },
+                    ],
+                });
+            } catch (err) {
+                if ((err as { kind?: string }).kind !== 'not_found') {
+                    throw err;
+                }
+                await invoke('write_yaml_file', {
+                    filePath: fileName,
+                    content: generateYAML(),
+                });
+            }
 
             setSuccess(`Test saved successfully: ${fileName}`);
             setError('');
         } catch (err) {
-            setError('Failed to save test: ' + err);
+            setError('Failed to save test: ' + errorMessage(err));
             setSuccess('');
         }
     };
@@ -1,4 +1,4 @@
-import { useState, useEffect } from 'react';
+import { useState, useEffect, useRef } from 'react';
 import {
     Box,
     Typography,
@@ -13,32 +13,118 @@ import {
     CircularProgress,
     Paper,
     LinearProgress,
+    Chip,
+    ListItemText,
 } from '@mui/material';
-import { PlayArrow, Stop, Refresh } from '@mui/icons-material';
+import { PlayArrow, Stop, Refresh, FolderOpen } from '@mui/icons-material';
 import { invoke } from '@tauri-apps/api/tauri';
+import { errorMessage } from '../errors';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+
+interface RunOutputEvent {
+    run_id: string;
+    stream: 'stdout' | 'stderr';
+    line: string;
+}
+
+interface TestDefinitionSummary {
+    path: string;
+    relative_path: string;
+    test_suite_name: string | null;
+    description: string | null;
+    version: string | null;
+    sections: string[];
+    error: string | null;
+}
+
+interface TestSummary {
+    total: number;
+    passed: number;
+    failed: number;
+    errors: number;
+    skipped: number;
+    duration_secs: number;
+}
+
+interface Diagnostic {
+    severity: 'error' | 'warning';
+    message: string;
+    path: string;
+    line: number;
+    column: number;
+}
+
+interface RunFinishedEvent {
+    run_id: string;
+    exit_code: number | null;
+    signal: number | null;
+    success: boolean;
+    elapsed_ms: number;
+    cancelled: boolean;
+    tests: TestSummary | null;
+    error: string | null;
+}
 
 export default function TestRunner() {
-    const [testFiles, setTestFiles] = useState<string[]>([]);
+    const [testFiles, setTestFiles] = useState<TestDefinitionSummary[]>([]);
     const [selectedTest, setSelectedTest] = useState('');
     const [running, setRunning] = useState(false);
     const [output, setOutput] = useState('');
     const [error, setError] = useState('');
+    const [failed, setFailed] = useState(false);
+    const [summary, setSummary] = useState<TestSummary | null>(null);
+    const [diagnostics, setDiagnostics] = useState<Diagnostic[]>([]);
+    const runIdRef = useRef<string | null>(null);
 
     useEffect(() => {
         loadTestFiles();
     }, []);
 
+    useEffect(() => {
+        const unlisteners: Promise<UnlistenFn>[] = [
+            listen<RunOutputEvent>('run-output', (event) => {
+                if (event.payload.run_id !== runIdRef.current) return;
+                setOutput((prev) => prev + event.payload.line + '\n');
+            }),
+            listen<RunFinishedEvent>('run-finished', (event) => {
+                const { run_id, exit_code, signal, success, elapsed_ms, error } = event.payload;
+                if (run_id !== runIdRef.current) return;
+                const elapsed = (elapsed_ms / 1000).toFixed(1);
+                if (error) {
+                    setError('Test execution failed: ' + error);
+                } else if (event.payload.cancelled) {
+                    setError(`Test run stopped after ${elapsed}s`);
+                } else if (signal !== null) {
+                    setError(`Test run killed by signal ${signal} after ${elapsed}s`);
+                } else if (!success) {
+                    setError(`Test run failed with exit code ${exit_code} after ${elapsed}s`);
+                }
+                setFailed(!success);
+                setSummary(event.payload.tests);
+                runIdRef.current = null;
+                setRunning(false);
+            }),
+            listen<{ category: string }[]>('workspace-changed', (event) => {
+                if (event.payload.some((change) => change.category === 'test_definition')) {
+                    loadTestFiles();
+                }
+            }),
+        ];
+
+        return () => {
+            unlisteners.forEach((p) => p.then((unlisten) => unlisten()));
+        };
+    }, []);
+
     const loadTestFiles = async () => {
         try {
-            const files = await invoke<string[]>('get_test_files', {
-                directory: './examples',
-            });
+            const files = await invoke<TestDefinitionSummary[]>('discover_test_definitions');
             setTestFiles(files);
-            if (files.length > 0) {
-                setSelectedTest(files[0]);
-            }
+            setSelectedTest((current) =>
+                files.some((file) => file.path === current) ? current : files[0]?.path ?? ''
+            );
         } catch (err) {
-            setError('Failed to load test files: ' + err);
+            setError('Failed to load test files: ' + errorMessage(err));
         }
     };
 
@@ -47,20 +133,63 @@ export default function TestRunner() {
 
         setRunning(true);
         setOutput('');
+        setFailed(false);
+        setSummary(null);
         setError('');
+        setDiagnostics([]);
 
         try {
-            const result = await invoke<string>('run_pytest', {
+            const problems = await invoke<Diagnostic[]>('validate_test_definition', {
+                path: selectedTest,
+            });
+            setDiagnostics(problems);
+            if (problems.some((problem) => problem.severity === 'error')) {
+                setError('Test definition has errors; fix them before running.');
+                setRunning(false);
+                return;
+            }
+        } catch (err) {
+            setError('Failed to validate test definition: ' + errorMessage(err));
+            setRunning(false);
+            return;
+        }
+
+        const runId = crypto.randomUUID();
+        runIdRef.current = runId;
+
+        try {
+            await invoke<string>('run_pytest_streaming', {
                 testFile: selectedTest,
+                runId,
             });
-            setOutput(result);
         } catch (err) {
-            setError('Test execution failed: ' + err);
-        } finally {
+            setError('Test execution failed: ' + errorMessage(err));
+            runIdRef.current = null;
             setRunning(false);
         }
     };
 
+    const openWorkspace = async () => {
+        try {
+            const root = await invoke<string | null>('open_workspace_root');
+            if (root) {
+                await loadTestFiles();
+            }
+        } catch (err) {
+            setError('Failed to open workspace: ' + errorMessage(err));
+        }
+    };
+
+    const stopTest = async () => {
+        if (!runIdRef.current) return;
+
+        try {
+            await invoke('cancel_run', { runId: runIdRef.current });
+        } catch (err) {
+            setError('Failed to stop test: ' + err);
+        }
+    };
+
     return (
         <Box>
             <Typography variant="h4" gutterBottom fontWeight={700}>
@@ -79,32 +208,59 @@ export default function TestRunner() {
                                 disabled={running}
                             >
                                 {testFiles.map((file) => (
-                                    <MenuItem key={file} value={file}>
-                                        {file.split('/').pop()}
+                                    <MenuItem key={file.path} value={file.path} disabled={!!file.error}>
+                                        <ListItemText
+                                            primary={file.test_suite_name ?? file.relative_path}
+                                            secondary={file.error ?? `${file.relative_path} · ${file.sections.join(', ')}`}
+                                        />
                                     </MenuItem>
                                 ))}
                             </Select>
                         </FormControl>
 
+                        {running ? (
+                            <Button
+                                variant="contained"
+                                color="error"
+                                size="large"
+                                startIcon={<Stop />}
+                                onClick={stopTest}
+                                sx={{ minWidth: 120 }}
+                            >
+                                Stop
+                            </Button>
+                        ) : (
+                            <Button
+                                variant="contained"
+                                size="large"
+                                startIcon={<PlayArrow />}
+                                onClick={runTest}
+                                disabled={!selectedTest}
+                                sx={{ minWidth: 120 }}
+                            >
+                                Run Test
+                            </Button>
+                        )}
+
                         <Button
-                            variant="contained"
+                            variant="outlined"
                             size="large"
-                            startIcon={running ? <Stop /> : <PlayArrow />}
-                            onClick={runTest}
-                            disabled={!selectedTest || running}
-                            sx={{ minWidth: 120 }}
+                            startIcon={<Refresh />}
+                            onClick={loadTestFiles}
+                            disabled={running}
                         >
-                            {running ? 'Running...' : 'Run Test'}
+                            Refresh
                         </Button>
 
                         <Button
                             variant="outlined"
                             size="large"
-                            startIcon={<Refresh />}
-                            onClick={loadTestFiles}
+                            startIcon={<FolderOpen />}
+                            onClick={openWorkspace}
                             disabled={running}
+                            sx={{ whiteSpace: 'nowrap', minWidth: 'auto' }}
                         >
-                            Refresh
+                            Open Workspace
                         </Button>
                     </Box>
                 </CardContent>
@@ -122,12 +278,32 @@ export default function TestRunner() {
                 </Card>
             )}
 
+            {summary && (
+                <Box display="flex" gap={1} mb={3}>
+                    <Chip label={`${summary.passed} passed`} color="success" />
+                    <Chip label={`${summary.failed} failed`} color={summary.failed ? 'error' : 'default'} />
+                    <Chip label={`${summary.errors} errors`} color={summary.errors ? 'error' : 'default'} />
+                    <Chip label={`${summary.skipped} skipped`} />
+                    <Chip label={`${summary.duration_secs.toFixed(1)}s`} variant="outlined" />
+                </Box>
+            )}
+
             {error && (
                 <Alert severity="error" sx={{ mb: 3 }}>
                     {error}
                 </Alert>
             )}
 
+            {diagnostics.length > 0 && (
+                <Box display="flex" flexDirection="column" gap={1} mb={3}>
+                    {diagnostics.map((diagnostic, index) => (
+                        <Alert key={index} severity={diagnostic.severity}>
+                            Line {diagnostic.line}, column {diagnostic.column}: {diagnostic.message}
+                        </Alert>
+                    ))}
+                </Box>
+            )}
+
             {output && (
                 <Card>
                     <CardContent>
@@ -138,7 +314,7 @@ export default function TestRunner() {
                             sx={{
                                 p: 2,
                                 bgcolor: '#000',
-                                color: '#0f0',
+                                color: failed ? '#f44' : '#0f0',
                                 fontFamily: 'monospace',
                                 fontSize: '0.875rem',
                                 maxHeight: '500px',